@@ -0,0 +1,998 @@
+use crate::config::{check_file, config_files, Config, FileReport};
+use crate::deps::{DependencyGraph, Direction};
+use crate::docker::{check_all_statuses, pull_images, BuildOptions, OpResult, ServiceStatus};
+use crate::logs::{save_logs, stream_logs, LogOptions};
+use crate::ops::{
+    build_and_start, print_pull_result, run_ordered, run_with_progress, services_to_recreate,
+    wait_until_ready,
+};
+use crate::ports::{conflicts_for_start, describe_conflict};
+use crate::service::Service;
+use crate::snapshot::{delete_snapshot, get_snapshot, list_snapshots, restore_plan, save_snapshot};
+use crate::ui::state_label;
+use colored::*;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage:
+  dockerstrator [directory]                 Open the interactive menu
+  dockerstrator [-C <directory>] <command>  Run a single command and exit
+
+Services can be named individually, as @group (see [groups] in the config)
+or with --all.
+
+Commands:
+  up [--wait] <service>... | --all
+                                 Start services (docker compose up -d)
+      --wait                     Wait until they are healthy or ready
+  down <service>... | --all      Stop services (docker compose down)
+  restart <service>... | --all   Restart services
+  pull [--recreate] <service>... | --all
+                                 Pull images; --recreate restarts running
+                                 services whose images changed
+  build [options] <service>... | --all
+                                 Build images, streaming the build output
+      --no-cache                 Do not use the build cache
+      --pull                     Pull newer versions of base images
+      --up                       Start the services after building
+  status [--format <format>]     Print the status of every service
+                                 (format: text, json, yaml, table)
+  logs [options] <service>... | --all
+                                 Follow logs
+      --tail <n>                 Only show the last n lines per service
+      --since <time>             Only show logs since a timestamp or duration (10m, 2h)
+      --timestamps               Show timestamps
+      --save                     Write a snapshot to a timestamped file instead of following
+  snapshot save [--stop] <name>  Record which services are running;
+                                 --stop then stops them
+  snapshot restore <name>        Start what the snapshot had running and
+                                 stop everything else
+  snapshot list | delete <name>  List or delete saved snapshots
+  config check                   Validate the global and project config files
+
+Options:
+  -C, --dir <directory>  Directory to scan (defaults to the current directory)
+  -h, --help             Show this help";
+
+pub struct Cli {
+    pub dir: PathBuf,
+    pub command: Option<Command>,
+}
+
+pub enum Command {
+    Up {
+        selection: Selection,
+        wait: bool,
+    },
+    Down(Selection),
+    Restart(Selection),
+    Pull {
+        selection: Selection,
+        recreate: bool,
+    },
+    Build {
+        selection: Selection,
+        options: BuildOptions,
+        start: bool,
+    },
+    Status(StatusFormat),
+    Logs {
+        selection: Selection,
+        options: LogOptions,
+        save: bool,
+    },
+    Snapshot(SnapshotCommand),
+    CheckConfig,
+    Help,
+}
+
+pub enum SnapshotCommand {
+    Save { name: String, stop: bool },
+    Restore(String),
+    List,
+    Delete(String),
+}
+
+#[derive(Clone, Copy)]
+pub enum StatusFormat {
+    Text,
+    Json,
+    Yaml,
+    Table,
+}
+
+pub struct Selection {
+    pub names: Vec<String>,
+    pub all: bool,
+}
+
+pub fn parse_args(args: Vec<String>) -> Result<Cli, String> {
+    let mut dir: Option<PathBuf> = None;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                return Ok(Cli {
+                    dir: dir.unwrap_or_else(|| PathBuf::from(".")),
+                    command: Some(Command::Help),
+                })
+            }
+            "-C" | "--dir" => match args.next() {
+                Some(path) => dir = Some(PathBuf::from(path)),
+                None => return Err(format!("{} requires a directory", arg)),
+            },
+            "up" | "down" | "restart" | "pull" | "build" | "status" | "logs" | "snapshot"
+            | "config" => {
+                let command = parse_command(&arg, args.collect())?;
+                return Ok(Cli {
+                    dir: dir.unwrap_or_else(|| PathBuf::from(".")),
+                    command: Some(command),
+                });
+            }
+            a if a.starts_with('-') => return Err(format!("Unknown option: {}", a)),
+            _ if dir.is_none() => dir = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    Ok(Cli {
+        dir: dir.unwrap_or_else(|| PathBuf::from(".")),
+        command: None,
+    })
+}
+
+fn parse_command(name: &str, args: Vec<String>) -> Result<Command, String> {
+    match name {
+        "up" => parse_up(args),
+        "down" => Ok(Command::Down(parse_selection(args)?)),
+        "restart" => Ok(Command::Restart(parse_selection(args)?)),
+        "pull" => {
+            let recreate = args.iter().any(|a| a == "--recreate");
+            let rest = args.into_iter().filter(|a| a != "--recreate").collect();
+            Ok(Command::Pull {
+                selection: parse_selection(rest)?,
+                recreate,
+            })
+        }
+        "build" => parse_build(args),
+        "logs" => parse_logs(args),
+        "status" => Ok(Command::Status(parse_status_format(args)?)),
+        "snapshot" => parse_snapshot(args),
+        "config" => match args.as_slice() {
+            [action] if action == "check" => Ok(Command::CheckConfig),
+            [] => Err("config requires an action: check".to_string()),
+            _ => Err(format!("Unknown config action: {}", args.join(" "))),
+        },
+        _ => Err(format!("Unknown command: {}", name)),
+    }
+}
+
+fn parse_status_format(args: Vec<String>) -> Result<StatusFormat, String> {
+    let mut format = StatusFormat::Text;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "-f" | "--format" => args.next(),
+            a if a.starts_with("--format=") => Some(a["--format=".len()..].to_string()),
+            _ => return Err(format!("Unexpected argument for status: {}", arg)),
+        };
+        format = match value.as_deref() {
+            Some("text") => StatusFormat::Text,
+            Some("json") => StatusFormat::Json,
+            Some("yaml") => StatusFormat::Yaml,
+            Some("table") => StatusFormat::Table,
+            Some(other) => {
+                return Err(format!(
+                    "Unknown format: {} (expected text, json, yaml or table)",
+                    other
+                ))
+            }
+            None => return Err("--format requires a value".to_string()),
+        };
+    }
+
+    Ok(format)
+}
+
+fn parse_up(args: Vec<String>) -> Result<Command, String> {
+    let wait = args.iter().any(|a| a == "--wait");
+    let rest = args.into_iter().filter(|a| a != "--wait").collect();
+    Ok(Command::Up {
+        selection: parse_selection(rest)?,
+        wait,
+    })
+}
+
+fn parse_snapshot(args: Vec<String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let action = args
+        .next()
+        .ok_or("snapshot requires an action: save, restore, list or delete")?;
+    let mut stop = false;
+    let mut names = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--stop" if action == "save" => stop = true,
+            a if a.starts_with('-') => return Err(format!("Unknown option: {}", a)),
+            _ => names.push(arg),
+        }
+    }
+
+    let name = |names: Vec<String>| match names.as_slice() {
+        [name] => Ok(name.clone()),
+        [] => Err(format!("snapshot {} requires a name", action)),
+        _ => Err(format!("snapshot {} takes a single name", action)),
+    };
+    let command = match action.as_str() {
+        "save" => SnapshotCommand::Save {
+            name: name(names)?,
+            stop,
+        },
+        "restore" => SnapshotCommand::Restore(name(names)?),
+        "delete" => SnapshotCommand::Delete(name(names)?),
+        "list" if names.is_empty() => SnapshotCommand::List,
+        "list" => return Err("snapshot list takes no arguments".to_string()),
+        _ => return Err(format!("Unknown snapshot action: {}", action)),
+    };
+    Ok(Command::Snapshot(command))
+}
+
+fn parse_build(args: Vec<String>) -> Result<Command, String> {
+    let mut options = BuildOptions::default();
+    let mut start = false;
+    let mut rest = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--no-cache" => options.no_cache = true,
+            "--pull" => options.pull = true,
+            "--up" => start = true,
+            _ => rest.push(arg),
+        }
+    }
+
+    Ok(Command::Build {
+        selection: parse_selection(rest)?,
+        options,
+        start,
+    })
+}
+
+fn parse_logs(args: Vec<String>) -> Result<Command, String> {
+    let mut options = LogOptions::default();
+    let mut save = false;
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tail" | "-n" => {
+                let value = args.next().ok_or("--tail requires a number")?;
+                let tail = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid --tail value: {}", value))?;
+                options.tail = Some(tail);
+            }
+            "--since" => options.since = Some(args.next().ok_or("--since requires a value")?),
+            "-t" | "--timestamps" => options.timestamps = true,
+            "--save" => save = true,
+            _ => rest.push(arg),
+        }
+    }
+
+    Ok(Command::Logs {
+        selection: parse_selection(rest)?,
+        options,
+        save,
+    })
+}
+
+fn parse_selection(args: Vec<String>) -> Result<Selection, String> {
+    let mut names = Vec::new();
+    let mut all = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "-a" | "--all" => all = true,
+            a if a.starts_with('-') => return Err(format!("Unknown option: {}", a)),
+            _ => {
+                if !names.contains(&arg) {
+                    names.push(arg);
+                }
+            }
+        }
+    }
+
+    if all && !names.is_empty() {
+        return Err("--all cannot be combined with service names".to_string());
+    }
+    if !all && names.is_empty() {
+        return Err("No services specified (pass service names or --all)".to_string());
+    }
+
+    Ok(Selection { names, all })
+}
+
+/// Resolves names and `@group` references against the discovered services.
+fn resolve_selection(
+    selection: &Selection,
+    services: &[Service],
+    config: &Config,
+) -> Result<Vec<Service>, String> {
+    if selection.all {
+        return Ok(services.to_vec());
+    }
+
+    let mut names: Vec<&str> = Vec::new();
+    for name in &selection.names {
+        match name.strip_prefix('@') {
+            Some(group) => match config.groups.get(group) {
+                Some(members) => {
+                    // Groups can list stacks from other checkouts; like the menu,
+                    // skip what this scan didn't find instead of failing
+                    for member in members {
+                        if services.iter().any(|s| &s.name == member) {
+                            names.push(member);
+                        } else {
+                            eprintln!(
+                                "{}",
+                                format!("Warning: {} in {} wasn't found, skipping", member, name)
+                                    .yellow()
+                            );
+                        }
+                    }
+                }
+                None => {
+                    let available: Vec<String> =
+                        config.groups.keys().map(|g| format!("@{}", g)).collect();
+                    return Err(format!(
+                        "Unknown group: {}\nAvailable: {}",
+                        name,
+                        if available.is_empty() {
+                            "none (define them under [groups] in the config)".to_string()
+                        } else {
+                            available.join(", ")
+                        }
+                    ));
+                }
+            },
+            None => names.push(name),
+        }
+    }
+
+    if names.is_empty() {
+        return Err(format!(
+            "None of {} were found in this directory",
+            selection.names.join(", ")
+        ));
+    }
+
+    let unknown: Vec<&str> = names
+        .iter()
+        .filter(|name| !services.iter().any(|s| &s.name == *name))
+        .copied()
+        .collect();
+
+    if !unknown.is_empty() {
+        let available: Vec<&str> = services.iter().map(|s| s.name.as_str()).collect();
+        return Err(format!(
+            "Unknown service(s): {}\nAvailable: {}",
+            unknown.join(", "),
+            available.join(", ")
+        ));
+    }
+
+    Ok(services
+        .iter()
+        .filter(|s| names.contains(&s.name.as_str()))
+        .cloned()
+        .collect())
+}
+
+/// Runs a single command without any prompt and returns the process exit code.
+pub fn run(command: Command, services: &[Service], config: &Config) -> i32 {
+    match command {
+        Command::Up { selection, wait } => {
+            let selected = match resolve_selection(&selection, services, config) {
+                Ok(selected) => selected,
+                Err(e) => return usage_error(&e),
+            };
+            warn_port_conflicts(&selected, services, config);
+            let order = Some(Direction::Start);
+            let results =
+                match run_selected(selected, services, config, &["up", "-d"], "started", order) {
+                    Ok(results) => results,
+                    Err(code) => return code,
+                };
+
+            let mut ok = results.iter().all(|r| r.ok);
+            if wait || config.wait_healthy {
+                let started: Vec<Service> = services
+                    .iter()
+                    .filter(|s| results.iter().any(|r| r.name == s.name && r.ok))
+                    .cloned()
+                    .collect();
+                if !started.is_empty() {
+                    ok &= wait_until_ready(&started, config);
+                }
+            }
+            if ok {
+                0
+            } else {
+                1
+            }
+        }
+        Command::Down(selection) => {
+            let order = Some(Direction::Stop);
+            run_operation(&selection, services, config, &["down"], "stopped", order)
+                .map_or_else(|code| code, |results| exit_code(&results))
+        }
+        Command::Restart(selection) => run_operation(
+            &selection,
+            services,
+            config,
+            &["restart"],
+            "restarted",
+            None,
+        )
+        .map_or_else(|code| code, |results| exit_code(&results)),
+        Command::Pull {
+            selection,
+            recreate,
+        } => run_pull(&selection, recreate, services, config),
+        Command::Build {
+            selection,
+            options,
+            start,
+        } => {
+            let mut selected = match resolve_selection(&selection, services, config) {
+                Ok(selected) => selected,
+                Err(e) => return usage_error(&e),
+            };
+            if selection.all {
+                selected.retain(|s| s.has_build());
+            }
+            let results = build_and_start(&selected, &options, start, config.legacy_compose);
+            if results.iter().all(|r| r.ok) {
+                0
+            } else {
+                1
+            }
+        }
+        Command::Status(format) => {
+            let statuses = check_all_statuses(services, config.legacy_compose);
+            if let Err(e) = print_statuses(&statuses, format) {
+                eprintln!("{}", format!("Error: {}", e).red());
+                return 1;
+            }
+            let failed: Vec<&ServiceStatus> =
+                statuses.iter().filter(|s| s.error.is_some()).collect();
+            for status in &failed {
+                let error = status.error.as_deref().unwrap_or_default();
+                eprintln!(
+                    "{}",
+                    format!("Error: can't query {}: {}", status.name, error).red()
+                );
+            }
+            if failed.is_empty() {
+                0
+            } else {
+                1
+            }
+        }
+        Command::Logs {
+            selection,
+            options,
+            save,
+        } => {
+            let selected = match resolve_selection(&selection, services, config) {
+                Ok(selected) => selected,
+                Err(e) => return usage_error(&e),
+            };
+            if !save {
+                stream_logs(&selected, config.legacy_compose, &options);
+                return 0;
+            }
+            match save_logs(&selected, config.legacy_compose, &options) {
+                Ok(path) => {
+                    println!("Logs saved to {}", path.display());
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{}", format!("Error saving logs: {}", e).red());
+                    1
+                }
+            }
+        }
+        Command::Snapshot(command) => run_snapshot(command, services, config),
+        Command::CheckConfig => check_config(),
+        Command::Help => {
+            println!("{}", USAGE);
+            0
+        }
+    }
+}
+
+pub fn print_config_report(report: &FileReport) {
+    for error in &report.errors {
+        eprintln!("{} {}", "ERROR".red(), error);
+    }
+    for warning in &report.warnings {
+        eprintln!("{} {}", "WARN".yellow(), warning);
+    }
+}
+
+/// Validates every config file that applies to the current directory.
+pub fn check_config() -> i32 {
+    let dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let reports: Vec<FileReport> = config_files(&dir)
+        .iter()
+        .filter_map(|path| check_file(path))
+        .collect();
+
+    if reports.is_empty() {
+        println!("No config files found, using defaults");
+    }
+    for report in &reports {
+        if report.is_clean() {
+            println!("{} {}", "OK".green(), report.path.display());
+        }
+        print_config_report(report);
+    }
+
+    if reports.iter().all(|r| r.errors.is_empty()) {
+        0
+    } else {
+        1
+    }
+}
+
+fn run_snapshot(command: SnapshotCommand, services: &[Service], config: &Config) -> i32 {
+    let result = match command {
+        SnapshotCommand::Save { name, stop } => {
+            let statuses = check_all_statuses(services, config.legacy_compose);
+            save_snapshot(&name, &statuses).map(|snapshot| {
+                println!(
+                    "Saved snapshot {}: {}",
+                    name,
+                    if snapshot.running.is_empty() {
+                        "nothing running".to_string()
+                    } else {
+                        snapshot.running.join(", ")
+                    }
+                );
+                let running: Vec<Service> = services
+                    .iter()
+                    .filter(|s| snapshot.running.contains(&s.name))
+                    .cloned()
+                    .collect();
+                if stop && !running.is_empty() {
+                    let order = Some(Direction::Stop);
+                    run_selected(running, services, config, &["down"], "stopped", order)
+                        .map_or_else(|code| code, |results| exit_code(&results))
+                } else {
+                    0
+                }
+            })
+        }
+        SnapshotCommand::Restore(name) => get_snapshot(&name).map(|snapshot| {
+            let statuses = check_all_statuses(services, config.legacy_compose);
+            let plan = restore_plan(&snapshot, services, &statuses);
+            for name in &plan.missing {
+                eprintln!(
+                    "{}",
+                    format!("Warning: {} is in the snapshot but wasn't found", name).yellow()
+                );
+            }
+            if plan.is_empty() {
+                println!("Already matches snapshot {}", name);
+                return 0;
+            }
+
+            let mut results = Vec::new();
+            for (selected, args, verb, direction) in [
+                (plan.to_stop, &["down"][..], "stopped", Direction::Stop),
+                (
+                    plan.to_start,
+                    &["up", "-d"][..],
+                    "started",
+                    Direction::Start,
+                ),
+            ] {
+                if selected.is_empty() {
+                    continue;
+                }
+                match run_selected(selected, services, config, args, verb, Some(direction)) {
+                    Ok(step) => results.extend(step),
+                    Err(code) => return code,
+                }
+            }
+            exit_code(&results)
+        }),
+        SnapshotCommand::List => {
+            for (name, snapshot) in list_snapshots() {
+                println!(
+                    "{}  {}  {}",
+                    name.cyan(),
+                    snapshot.created.bright_black(),
+                    snapshot.running.join(", ")
+                );
+            }
+            Ok(0)
+        }
+        SnapshotCommand::Delete(name) => delete_snapshot(&name).map(|_| {
+            println!("Deleted snapshot {}", name);
+            0
+        }),
+    };
+
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", format!("Error: {}", e).red());
+        1
+    })
+}
+
+fn print_statuses(statuses: &[ServiceStatus], format: StatusFormat) -> Result<(), String> {
+    match format {
+        StatusFormat::Text => {
+            for status in statuses {
+                println!("{}: {}", status.name.cyan(), state_label(status.state));
+            }
+        }
+        StatusFormat::Json => {
+            let json = serde_json::to_string_pretty(statuses).map_err(|e| e.to_string())?;
+            println!("{}", json);
+        }
+        StatusFormat::Yaml => {
+            let yaml = serde_yaml::to_string(statuses).map_err(|e| e.to_string())?;
+            print!("{}", yaml);
+        }
+        StatusFormat::Table => {
+            let name_width = statuses
+                .iter()
+                .map(|s| s.name.len())
+                .chain(std::iter::once("NAME".len()))
+                .max()
+                .unwrap_or(0);
+            let file_width = statuses
+                .iter()
+                .map(|s| s.compose_file.len())
+                .chain(std::iter::once("COMPOSE FILE".len()))
+                .max()
+                .unwrap_or(0);
+            println!(
+                "{:<nw$}  {:<9}  {:>10}  {:<fw$}  PATH",
+                "NAME",
+                "STATUS",
+                "CONTAINERS",
+                "COMPOSE FILE",
+                nw = name_width,
+                fw = file_width
+            );
+            for s in statuses {
+                println!(
+                    "{:<nw$}  {:<9}  {:>10}  {:<fw$}  {}",
+                    s.name,
+                    s.state.label(),
+                    format!("{}/{}", s.running_count(), s.containers.len()),
+                    s.compose_file,
+                    s.path.display(),
+                    nw = name_width,
+                    fw = file_width
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn warn_port_conflicts(selected: &[Service], services: &[Service], config: &Config) {
+    let running: Vec<String> = check_all_statuses(services, config.legacy_compose)
+        .into_iter()
+        .filter(|s| s.running)
+        .map(|s| s.name)
+        .collect();
+    for conflict in conflicts_for_start(selected, services, &running) {
+        eprintln!(
+            "{}",
+            format!("Warning: {}", describe_conflict(&conflict)).yellow()
+        );
+    }
+}
+
+/// Docker's output is captured during parallel runs; show what the failed
+/// services printed so scripts and CI logs still have it.
+fn print_failures(results: &[OpResult]) {
+    for result in results.iter().filter(|r| !r.ok) {
+        eprintln!("\n{}", format!("==> {} <==", result.name).red());
+        let stderr = result.stderr.trim_end();
+        if stderr.is_empty() {
+            eprintln!("{}", result.stdout.trim_end());
+        } else {
+            eprintln!("{}", stderr);
+        }
+    }
+}
+
+fn run_operation(
+    selection: &Selection,
+    services: &[Service],
+    config: &Config,
+    args: &[&str],
+    verb: &str,
+    order: Option<Direction>,
+) -> Result<Vec<OpResult>, i32> {
+    let selected = resolve_selection(selection, services, config).map_err(|e| usage_error(&e))?;
+    run_selected(selected, services, config, args, verb, order)
+}
+
+fn run_selected(
+    selected: Vec<Service>,
+    services: &[Service],
+    config: &Config,
+    args: &[&str],
+    verb: &str,
+    order: Option<Direction>,
+) -> Result<Vec<OpResult>, i32> {
+    let results = match order {
+        Some(direction) => {
+            let graph = DependencyGraph::build(services, config);
+            match run_ordered(&selected, &graph, direction, args, config.legacy_compose) {
+                Ok(results) => results,
+                Err(e) => {
+                    eprintln!("{}", format!("Error: {}", e).red());
+                    return Err(1);
+                }
+            }
+        }
+        None => run_with_progress(selected, args, config.legacy_compose),
+    };
+    let ok_count = results.iter().filter(|r| r.ok).count();
+    println!("{}/{} services {}", ok_count, results.len(), verb);
+    print_failures(&results);
+    Ok(results)
+}
+
+fn exit_code(results: &[OpResult]) -> i32 {
+    if results.iter().all(|r| r.ok) {
+        0
+    } else {
+        1
+    }
+}
+
+fn run_pull(selection: &Selection, recreate: bool, services: &[Service], config: &Config) -> i32 {
+    let selected = match resolve_selection(selection, services, config) {
+        Ok(selected) => selected,
+        Err(e) => return usage_error(&e),
+    };
+
+    let results = pull_images(selected.clone(), config.legacy_compose, |_| {});
+    for result in &results {
+        print_pull_result(result);
+    }
+    let pulled: Vec<OpResult> = results.iter().map(|r| r.result.clone()).collect();
+    let ok_count = pulled.iter().filter(|r| r.ok).count();
+    println!("{}/{} services pulled", ok_count, results.len());
+    print_failures(&pulled);
+    let mut code = if ok_count == results.len() { 0 } else { 1 };
+
+    if recreate {
+        let to_recreate = services_to_recreate(&selected, &results, config.legacy_compose);
+        if !to_recreate.is_empty() {
+            let results = run_with_progress(to_recreate, &["up", "-d"], config.legacy_compose);
+            let ok_count = results.iter().filter(|r| r.ok).count();
+            println!("{}/{} services recreated", ok_count, results.len());
+            print_failures(&results);
+            if ok_count != results.len() {
+                code = 1;
+            }
+        }
+    }
+
+    code
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("{}", format!("Error: {}", message).red());
+    2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Cli, String> {
+        parse_args(line.split_whitespace().map(String::from).collect())
+    }
+
+    fn command(line: &str) -> Command {
+        match parse(line) {
+            Ok(Cli {
+                command: Some(command),
+                ..
+            }) => command,
+            Ok(_) => panic!("no command in {:?}", line),
+            Err(e) => panic!("{:?}: {}", line, e),
+        }
+    }
+
+    fn error(line: &str) -> String {
+        match parse(line) {
+            Ok(_) => panic!("{:?} should not parse", line),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parses_dir_and_selection() {
+        let cli = parse("-C /work up --wait api db api").unwrap();
+        assert_eq!(cli.dir, PathBuf::from("/work"));
+        match cli.command {
+            Some(Command::Up { selection, wait }) => {
+                assert_eq!(selection.names, ["api", "db"]);
+                assert!(!selection.all);
+                assert!(wait);
+            }
+            _ => panic!("expected up"),
+        }
+
+        let cli = parse("/work").unwrap();
+        assert_eq!(cli.dir, PathBuf::from("/work"));
+        assert!(cli.command.is_none());
+    }
+
+    #[test]
+    fn all_excludes_names() {
+        assert!(matches!(
+            command("down --all"),
+            Command::Down(Selection { all: true, .. })
+        ));
+        assert_eq!(
+            error("down --all api"),
+            "--all cannot be combined with service names"
+        );
+        assert_eq!(
+            error("restart"),
+            "No services specified (pass service names or --all)"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert_eq!(error("--verbose"), "Unknown option: --verbose");
+        assert_eq!(error("up --force api"), "Unknown option: --force");
+        assert_eq!(
+            error("snapshot restore --stop dev"),
+            "Unknown option: --stop"
+        );
+        assert_eq!(
+            error("status --wide"),
+            "Unexpected argument for status: --wide"
+        );
+        assert_eq!(error("/a /b"), "Unexpected argument: /b");
+    }
+
+    #[test]
+    fn parses_status_formats() {
+        let format = |line: &str| match command(line) {
+            Command::Status(format) => format,
+            _ => panic!("expected status"),
+        };
+        assert!(matches!(format("status"), StatusFormat::Text));
+        assert!(matches!(format("status --format=json"), StatusFormat::Json));
+        assert!(matches!(format("status -f yaml"), StatusFormat::Yaml));
+        assert!(matches!(
+            format("status --format table"),
+            StatusFormat::Table
+        ));
+        assert_eq!(
+            error("status --format=xml"),
+            "Unknown format: xml (expected text, json, yaml or table)"
+        );
+        assert_eq!(error("status --format"), "--format requires a value");
+    }
+
+    #[test]
+    fn parses_log_options() {
+        match command("logs --tail 50 --since 10m -t --save api") {
+            Command::Logs {
+                selection,
+                options,
+                save,
+            } => {
+                assert_eq!(selection.names, ["api"]);
+                assert_eq!(options.tail, Some(50));
+                assert_eq!(options.since.as_deref(), Some("10m"));
+                assert!(options.timestamps);
+                assert!(save);
+            }
+            _ => panic!("expected logs"),
+        }
+        assert_eq!(error("logs --tail many api"), "Invalid --tail value: many");
+    }
+
+    #[test]
+    fn parses_snapshot_actions() {
+        assert!(matches!(
+            command("snapshot save --stop dev"),
+            Command::Snapshot(SnapshotCommand::Save { ref name, stop: true }) if name == "dev"
+        ));
+        assert!(matches!(
+            command("snapshot list"),
+            Command::Snapshot(SnapshotCommand::List)
+        ));
+        assert_eq!(
+            error("snapshot restore"),
+            "snapshot restore requires a name"
+        );
+        assert_eq!(
+            error("snapshot delete a b"),
+            "snapshot delete takes a single name"
+        );
+        assert_eq!(
+            error("snapshot rename a"),
+            "Unknown snapshot action: rename"
+        );
+    }
+
+    fn services(names: &[&str]) -> Vec<Service> {
+        names
+            .iter()
+            .map(|name| Service {
+                name: name.to_string(),
+                path: PathBuf::from("/nonexistent").join(name),
+                compose_file: "docker-compose.yml".to_string(),
+            })
+            .collect()
+    }
+
+    fn resolve(line: &str, config: &Config) -> Result<Vec<String>, String> {
+        let selection = parse_selection(line.split_whitespace().map(String::from).collect())?;
+        let selected = resolve_selection(&selection, &services(&["api", "db", "web"]), config)?;
+        Ok(selected.into_iter().map(|s| s.name).collect())
+    }
+
+    fn groups(groups: &[(&str, &[&str])]) -> Config {
+        let mut config = Config::default();
+        for (name, members) in groups {
+            let members = members.iter().map(|m| m.to_string()).collect();
+            config.groups.insert(name.to_string(), members);
+        }
+        config
+    }
+
+    #[test]
+    fn expands_groups() {
+        let config = groups(&[("backend", &["db", "api"]), ("front", &["web"])]);
+        assert_eq!(resolve("@backend", &config).unwrap(), ["api", "db"]);
+        assert_eq!(resolve("@front db", &config).unwrap(), ["db", "web"]);
+        assert_eq!(resolve("--all", &config).unwrap(), ["api", "db", "web"]);
+    }
+
+    #[test]
+    fn skips_undiscovered_group_members() {
+        let config = groups(&[("backend", &["api", "elsewhere"]), ("gone", &["elsewhere"])]);
+        assert_eq!(resolve("@backend", &config).unwrap(), ["api"]);
+        assert_eq!(
+            resolve("@gone", &config).unwrap_err(),
+            "None of @gone were found in this directory"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_names_and_groups() {
+        let config = groups(&[("backend", &["api"])]);
+        assert_eq!(
+            resolve("api elsewhere", &config).unwrap_err(),
+            "Unknown service(s): elsewhere\nAvailable: api, db, web"
+        );
+        assert_eq!(
+            resolve("@nope", &config).unwrap_err(),
+            "Unknown group: @nope\nAvailable: @backend"
+        );
+    }
+}
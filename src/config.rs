@@ -1,12 +1,74 @@
-use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use toml_edit::{DocumentMut, ImDocument, Item, TableLike};
+
+/// Per-repository settings, looked up in the target directory and its parents.
+pub const PROJECT_CONFIG_FILE: &str = ".dockerstrator.toml";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Written as 0 when unlimited, since TOML has no null
+    #[serde(serialize_with = "serialize_depth")]
     pub max_depth: Option<usize>,
+    #[serde(default)]
     pub excluded_dirs: Vec<String>,
     #[serde(default)]
     pub legacy_compose: bool,
+    /// Most docker commands run at once; 0 for no limit
+    #[serde(default = "default_max_parallel")]
+    pub max_parallel: usize,
+    /// Seconds before a docker command is killed; 0 to wait forever
+    #[serde(default = "default_operation_timeout")]
+    pub operation_timeout: u64,
+    /// Wait for started services to become healthy before reporting back
+    #[serde(default)]
+    pub wait_healthy: bool,
+    /// Seconds to wait for a started service to become healthy
+    #[serde(default = "default_health_timeout")]
+    pub health_timeout: u64,
+    /// Group name -> stacks, selectable together as `@name`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Stack name -> readiness probe used instead of Docker healthchecks
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub probes: BTreeMap<String, Probe>,
+    /// Stack name -> stacks that must be running before it starts
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, Vec<String>>,
+    /// Files this config was layered from
+    #[serde(skip)]
+    pub layers: Layers,
+}
+
+/// Readiness check for a stack: a TCP port that accepts connections, or an
+/// http:// URL that answers with a 2xx/3xx status.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Probe {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<String>,
+}
+
+fn serialize_depth<S: Serializer>(depth: &Option<usize>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(depth.unwrap_or(0) as u64)
+}
+
+fn default_max_parallel() -> usize {
+    8
+}
+
+fn default_operation_timeout() -> u64 {
+    0
+}
+
+fn default_health_timeout() -> u64 {
+    120
 }
 
 impl Default for Config {
@@ -15,6 +77,14 @@ impl Default for Config {
             max_depth: Some(7),
             excluded_dirs: Vec::new(),
             legacy_compose: false,
+            max_parallel: default_max_parallel(),
+            operation_timeout: default_operation_timeout(),
+            wait_healthy: false,
+            health_timeout: default_health_timeout(),
+            groups: BTreeMap::new(),
+            probes: BTreeMap::new(),
+            dependencies: BTreeMap::new(),
+            layers: Layers::default(),
         }
     }
 }
@@ -27,29 +97,582 @@ pub fn get_config_path() -> PathBuf {
         .join("config.toml")
 }
 
+/// The global config with any project files on top. Each top-level key a
+/// project file sets replaces the value from the layers below it, and the
+/// file nearest to the target directory wins.
+#[derive(Debug, Clone, Default)]
+pub struct Layers {
+    /// Directory project files were looked up from
+    dir: PathBuf,
+    /// Keys actually present in the global file
+    global_keys: Vec<String>,
+    /// The global config with defaults filled in
+    base: Table,
+    /// Project files from the outermost directory inwards
+    projects: Vec<(PathBuf, Table)>,
+    /// Project file settings are saved to; None for the global file
+    save_to: Option<PathBuf>,
+    /// Files that had errors or warnings when loaded
+    reports: Vec<FileReport>,
+}
+
+/// What's wrong with a config file, as "path:line:column: message" lines.
+/// A file with errors is ignored; warnings don't stop it from loading.
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl FileReport {
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty() && self.warnings.is_empty()
+    }
+}
+
+impl Layers {
+    fn load(dir: &Path) -> Layers {
+        let mut reports = Vec::new();
+        let global = read_layer(&get_config_path(), &mut reports);
+        let base = global
+            .clone()
+            .and_then(parse)
+            .map(|config| to_table(&config))
+            .unwrap_or_else(|| to_table(&Config::default()));
+
+        let projects: Vec<(PathBuf, Table)> = config_files(dir)
+            .into_iter()
+            .skip(1)
+            .filter_map(|path| read_layer(&path, &mut reports).map(|table| (path, table)))
+            .collect();
+
+        Layers {
+            dir: dir.to_path_buf(),
+            global_keys: global
+                .map(|t| t.keys().cloned().collect())
+                .unwrap_or_default(),
+            base,
+            save_to: projects.last().map(|(path, _)| path.clone()),
+            projects,
+            reports,
+        }
+    }
+
+    /// Settings from the global file and the first `count` project files.
+    fn merged(&self, count: usize) -> Table {
+        let mut table = self.base.clone();
+        for (_, layer) in &self.projects[..count] {
+            table.extend(layer.clone());
+        }
+        table
+    }
+
+    /// Problems found in the files this config was loaded from.
+    pub fn reports(&self) -> &[FileReport] {
+        &self.reports
+    }
+
+    /// Where a top-level setting comes from: "default", "global" or the
+    /// project file, relative to the target directory.
+    pub fn source(&self, key: &str) -> String {
+        match self
+            .projects
+            .iter()
+            .rev()
+            .find(|(_, t)| t.contains_key(key))
+        {
+            Some((path, _)) => self.relative(path),
+            None if self.global_keys.iter().any(|k| k == key) => "global".to_string(),
+            None => "default".to_string(),
+        }
+    }
+
+    fn relative(&self, path: &Path) -> String {
+        let up = self
+            .dir
+            .ancestors()
+            .position(|ancestor| Some(ancestor) == path.parent())
+            .unwrap_or(0);
+        format!("{}{}", "../".repeat(up), PROJECT_CONFIG_FILE)
+    }
+
+    pub fn save_path(&self) -> PathBuf {
+        self.save_to.clone().unwrap_or_else(get_config_path)
+    }
+
+    /// Switches saves between the global file and the nearest project file,
+    /// which is created in the target directory if there is none yet.
+    pub fn toggle_save_target(&mut self) {
+        self.save_to = match self.save_to {
+            Some(_) => None,
+            None => Some(
+                self.projects
+                    .last()
+                    .map(|(path, _)| path.clone())
+                    .unwrap_or_else(|| self.dir.join(PROJECT_CONFIG_FILE)),
+            ),
+        };
+    }
+}
+
+/// 1-based line and column of a byte offset.
+fn position(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+/// `path:line:column: message` for a problem at `offset` in `contents`.
+fn located(path: &Path, contents: &str, offset: Option<usize>, message: &str) -> String {
+    match offset {
+        Some(offset) => {
+            let (line, column) = position(contents, offset);
+            format!("{}:{}:{}: {}", path.display(), line, column, message)
+        }
+        None => format!("{}: {}", path.display(), message),
+    }
+}
+
+/// A TOML parse error pointing at where it happened in `path`.
+pub fn toml_error(path: &Path, contents: &str, e: &toml::de::Error) -> String {
+    let message = e.message().trim().replace('\n', ", ");
+    located(path, contents, e.span().map(|span| span.start), &message)
+}
+
+fn check_contents(path: &Path, contents: &str) -> FileReport {
+    let at = |offset: Option<usize>, message: &str| located(path, contents, offset, message);
+    let mut report = FileReport {
+        path: path.to_path_buf(),
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    if let Err(e) = toml::from_str::<Config>(contents) {
+        report.errors.push(toml_error(path, contents, &e));
+        return report;
+    }
+    // Unknown keys would otherwise be dropped without a word, typos included
+    if let Ok(document) = ImDocument::parse(contents) {
+        let mut unknown = |table: &dyn TableLike, known: &[&str], prefix: &str| {
+            for (name, _) in table.iter() {
+                if !known.contains(&name) {
+                    let offset = table
+                        .get_key_value(name)
+                        .and_then(|(key, _)| key.span())
+                        .map(|span| span.start);
+                    let message = format!("unknown key `{}{}`", prefix, name);
+                    report.warnings.push(at(offset, &message));
+                }
+            }
+        };
+        unknown(document.as_table(), field_names::<Config>(), "");
+        if let Some(probes) = document.get("probes").and_then(Item::as_table_like) {
+            for (stack, probe) in probes.iter() {
+                if let Some(probe) = probe.as_table_like() {
+                    let prefix = format!("probes.{}.", stack);
+                    unknown(probe, field_names::<Probe>(), &prefix);
+                }
+            }
+        }
+    }
+    report
+}
+
+/// The keys `T` reads, taken from its derived `Deserialize`.
+fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    struct Fields<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for Fields<'_> {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("only the fields are needed"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Fields(&mut fields));
+    fields
+}
+
+/// Validates one config file; None if it doesn't exist.
+pub fn check_file(path: &Path) -> Option<FileReport> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Some(check_contents(path, &contents)),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => Some(FileReport {
+            path: path.to_path_buf(),
+            errors: vec![format!("{}: {}", path.display(), e)],
+            warnings: Vec::new(),
+        }),
+    }
+}
+
+/// The global file, then project files for `dir` from the outermost
+/// directory inwards, whether or not they exist.
+pub fn config_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = dir
+        .ancestors()
+        .map(|ancestor| ancestor.join(PROJECT_CONFIG_FILE))
+        .collect();
+    files.push(get_config_path());
+    files.reverse();
+    files
+}
+
+/// Reads a layer, recording what's wrong with it. Files with errors are left
+/// out rather than half applied.
+fn read_layer(path: &Path, reports: &mut Vec<FileReport>) -> Option<Table> {
+    let report = check_file(path)?;
+    let usable = report.errors.is_empty();
+    if !report.is_clean() {
+        reports.push(report);
+    }
+    if usable {
+        fs::read_to_string(path).ok()?.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn parse(table: Table) -> Option<Config> {
+    let mut config: Config = Value::Table(table).try_into().ok()?;
+    if config.max_depth == Some(0) {
+        config.max_depth = None;
+    }
+    Some(config)
+}
+
+fn to_table(config: &Config) -> Table {
+    match Value::try_from(config) {
+        Ok(Value::Table(table)) => table,
+        _ => Table::new(),
+    }
+}
+
 pub fn load_config() -> Config {
-    let config_path = get_config_path();
+    let dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let layers = Layers::load(&dir);
+    let mut config = parse(layers.merged(layers.projects.len())).unwrap_or_default();
+    config.layers = layers;
+    config
+}
+
+/// What a save wrote.
+pub struct Saved {
+    pub path: PathBuf,
+    /// Whether any setting was written to `path`
+    pub written: bool,
+    /// Changed settings a project file overrides, so the global file can't
+    /// change them here; key -> that file
+    pub overridden: Vec<(String, String)>,
+}
+
+/// `value` as it would be written under `key` in a config file.
+fn to_item(key: &str, value: Value) -> Result<Item, String> {
+    let mut single = Table::new();
+    single.insert(key.to_string(), value);
+    let contents = toml::to_string_pretty(&single).map_err(|e| e.to_string())?;
+    let mut document = contents.parse::<DocumentMut>().map_err(|e| e.to_string())?;
+    Ok(document.remove(key).unwrap_or_default())
+}
 
-    if config_path.exists() {
-        if let Ok(contents) = fs::read_to_string(&config_path) {
-            if let Ok(config) = toml::from_str(&contents) {
-                return config;
+/// Updates `item`, written under `key`, to `value`. Parts already holding
+/// the same value are left alone, so their formatting and comments stay.
+fn update_item(item: &mut Item, key: &str, value: &Value, position: usize) -> Result<(), String> {
+    match (&mut *item, value) {
+        (Item::Table(table), Value::Table(values)) => {
+            let stale: Vec<String> = table
+                .iter()
+                .map(|(k, _)| k.to_string())
+                .filter(|k| !values.contains_key(k))
+                .collect();
+            for k in stale {
+                table.remove(&k);
+            }
+            let position = last_position(table).max(table.position().unwrap_or(position));
+            for (k, v) in values {
+                match table.get_mut(k) {
+                    Some(child) => update_item(child, k, v, position)?,
+                    None => {
+                        let mut child = to_item(k, v.clone())?;
+                        place(&mut child, position);
+                        table.insert(k, child);
+                    }
+                }
             }
         }
+        (Item::Value(old), _) if same_value(old, value) => {}
+        _ => {
+            let mut fresh = to_item(key, value.clone())?;
+            // Comments around the old value belong to the new one
+            match (&*item, &mut fresh) {
+                (Item::Value(old), Item::Value(new)) => *new.decor_mut() = old.decor().clone(),
+                (Item::Table(old), Item::Table(new)) => *new.decor_mut() = old.decor().clone(),
+                _ => {}
+            }
+            let position = item
+                .as_table()
+                .and_then(|table| table.position())
+                .unwrap_or(position);
+            place(&mut fresh, position);
+            *item = fresh;
+        }
     }
+    Ok(())
+}
+
+fn same_value(item: &toml_edit::Value, value: &Value) -> bool {
+    let mut bare = item.clone();
+    bare.decor_mut().clear();
+    format!("value = {}", bare)
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .as_ref()
+        == Some(value)
+}
+
+/// Puts `item` and the tables nested in it at `position` in the document,
+/// each after a blank line.
+fn place(item: &mut Item, position: usize) {
+    if let Item::Table(table) = item {
+        table.set_position(position);
+        let prefix = table.decor().prefix().and_then(|p| p.as_str());
+        if prefix.unwrap_or("").is_empty() {
+            table.decor_mut().set_prefix("\n");
+        }
+        for (_, child) in table.iter_mut() {
+            place(child, position);
+        }
+    }
+}
+
+/// Position of the last table in `table`, or 0 if there is none.
+fn last_position(table: &toml_edit::Table) -> usize {
+    table
+        .iter()
+        .filter_map(|(_, item)| item.as_table())
+        .map(|child| child.position().unwrap_or(0).max(last_position(child)))
+        .max()
+        .unwrap_or(0)
+}
 
-    Config::default()
+/// Settings that differ from what the config files set when loaded.
+fn changed_keys(layers: &Layers, table: &Table) -> Vec<String> {
+    let loaded = parse(layers.merged(layers.projects.len()))
+        .map(|config| to_table(&config))
+        .unwrap_or_default();
+    let mut keys: Vec<String> = table.keys().chain(loaded.keys()).cloned().collect();
+    keys.sort();
+    keys.dedup();
+    keys.retain(|key| table.get(key) != loaded.get(key));
+    keys
 }
 
-pub fn save_config(config: &Config) -> Result<(), String> {
-    let config_path = get_config_path();
+/// Writes the settings changed since loading to the file Settings saves to,
+/// leaving everything else in it, comments included, as it was. Settings a
+/// project file overrides aren't saved to the global file, since they
+/// wouldn't take effect here.
+pub fn save_config(config: &mut Config) -> Result<Saved, String> {
+    let layers = &config.layers;
+    let table = to_table(config);
+    let mut changed = changed_keys(layers, &table);
+    let mut overridden = Vec::new();
 
+    if layers.save_to.is_none() {
+        changed.retain(|key| {
+            let in_project = layers.projects.iter().any(|(_, t)| t.contains_key(key));
+            if in_project {
+                overridden.push((key.clone(), layers.source(key)));
+            }
+            !in_project
+        });
+    }
+    let written = !changed.is_empty();
+
+    let config_path = layers.save_path();
+    if let Some(report) = check_file(&config_path) {
+        if !report.errors.is_empty() {
+            return Err(format!(
+                "{} has errors, so it won't be overwritten:\n{}",
+                config_path.display(),
+                report.errors.join("\n")
+            ));
+        }
+    }
     if let Some(parent) = config_path.parent() {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
 
-    let contents = toml::to_string_pretty(config).map_err(|e| e.to_string())?;
-    fs::write(&config_path, contents).map_err(|e| e.to_string())?;
+    if !written {
+        return Ok(Saved {
+            path: config_path,
+            written,
+            overridden,
+        });
+    }
+    let mut document = match fs::read_to_string(&config_path) {
+        Ok(contents) => contents.parse::<DocumentMut>().map_err(|e| e.to_string())?,
+        Err(e) if e.kind() == ErrorKind::NotFound => DocumentMut::new(),
+        Err(e) => return Err(format!("{}: {}", config_path.display(), e)),
+    };
+    for key in &changed {
+        let value = match table.get(key) {
+            Some(value) => value.clone(),
+            // An emptied table isn't serialized, but in a project file it
+            // still has to override the layers below
+            None if layers.save_to.is_some() => Value::Table(Table::new()),
+            None => {
+                document.remove(key);
+                continue;
+            }
+        };
+        let position = last_position(document.as_table()) + 1;
+        match document.get_mut(key) {
+            Some(item) => update_item(item, key, &value, position)?,
+            None => {
+                let mut item = to_item(key, value)?;
+                place(&mut item, position);
+                document.insert(key, item);
+            }
+        }
+    }
+    fs::write(&config_path, document.to_string().trim_start()).map_err(|e| e.to_string())?;
 
-    Ok(())
+    let save_to = layers.save_to.clone();
+    config.layers = Layers::load(&layers.dir);
+    config.layers.save_to = save_to;
+    Ok(Saved {
+        path: config_path,
+        written,
+        overridden,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(contents: &str) -> Table {
+        contents.parse().unwrap()
+    }
+
+    fn layers(projects: &[&str]) -> Layers {
+        Layers {
+            dir: PathBuf::from("/work/shop/api"),
+            base: to_table(&Config::default()),
+            projects: projects
+                .iter()
+                .enumerate()
+                .map(|(i, contents)| (PathBuf::from(format!("/{}", i)), table(contents)))
+                .collect(),
+            ..Layers::default()
+        }
+    }
+
+    #[test]
+    fn inner_project_files_win() {
+        let layers = layers(&[
+            "max_parallel = 2\nwait_healthy = true",
+            "max_parallel = 4\n[groups]\nfront = [\"web\"]",
+        ]);
+        let config = parse(layers.merged(2)).unwrap();
+        assert_eq!(config.max_parallel, 4);
+        assert!(config.wait_healthy);
+        assert_eq!(config.groups["front"], ["web"]);
+        assert_eq!(config.health_timeout, default_health_timeout());
+
+        let outer = parse(layers.merged(1)).unwrap();
+        assert_eq!(outer.max_parallel, 2);
+        assert!(outer.groups.is_empty());
+    }
+
+    #[test]
+    fn tables_replace_rather_than_merge() {
+        let layers = layers(&["[groups]\nfront = [\"web\"]", "[groups]\nback = [\"api\"]"]);
+        let config = parse(layers.merged(2)).unwrap();
+        assert_eq!(config.groups.keys().collect::<Vec<_>>(), ["back"]);
+    }
+
+    #[test]
+    fn zero_depth_means_unlimited() {
+        let config = parse(table("max_depth = 0")).unwrap();
+        assert_eq!(config.max_depth, None);
+    }
+
+    #[test]
+    fn positions_are_one_based() {
+        let contents = "a = 1\nbé = 2\n";
+        assert_eq!(position(contents, 0), (1, 1));
+        assert_eq!(position(contents, 6), (2, 1));
+        assert_eq!(position(contents, 9), (2, 3));
+        assert_eq!(position(contents, 100), (3, 1));
+    }
+
+    #[test]
+    fn reports_errors_with_line_and_column() {
+        let path = Path::new("config.toml");
+        let report = check_contents(path, "max_parallel = 2\nwait_healthy = \"yes\"\n");
+        assert_eq!(report.errors.len(), 1);
+        assert!(
+            report.errors[0].starts_with("config.toml:2:16: "),
+            "{}",
+            report.errors[0]
+        );
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn warns_about_unknown_keys() {
+        let path = Path::new("config.toml");
+        let contents = "max_paralel = 2\n\n[probes.api]\ntcp = \"localhost:80\"\nhtp = \"x\"\n";
+        let report = check_contents(path, contents);
+        assert!(report.errors.is_empty());
+        assert_eq!(
+            report.warnings,
+            [
+                "config.toml:1:1: unknown key `max_paralel`",
+                "config.toml:5:1: unknown key `probes.api.htp`",
+            ]
+        );
+        assert!(check_contents(path, "max_parallel = 2\n").is_clean());
+    }
+
+    #[test]
+    fn updates_keep_comments_and_unchanged_values() {
+        let mut document: DocumentMut =
+            "# settings\nmax_parallel = 2 # pinned\n\n[groups]\nfront = [\"web\"] # ui\n"
+                .parse()
+                .unwrap();
+        let position = last_position(document.as_table()) + 1;
+        let item = document.get_mut("max_parallel").unwrap();
+        update_item(item, "max_parallel", &Value::Integer(4), position).unwrap();
+        let groups = table("front = [\"web\"]\nback = [\"api\"]");
+        let item = document.get_mut("groups").unwrap();
+        update_item(item, "groups", &Value::Table(groups), position).unwrap();
+
+        assert_eq!(
+            document.to_string(),
+            "# settings\nmax_parallel = 4 # pinned\n\n[groups]\nfront = [\"web\"] # ui\nback = [\"api\"]\n"
+        );
+    }
 }
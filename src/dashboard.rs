@@ -0,0 +1,262 @@
+use crate::config::Config;
+use crate::docker::{run_docker_compose, OpResult, ServiceStatus};
+use crate::events::{current_statuses, refresh_statuses, tracker_version};
+use crate::service::Service;
+use crate::ui::state_color;
+use colored::*;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{poll, read, Event, KeyCode, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use std::collections::HashMap;
+use std::io::{stdout, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Re-reads every service's status in the background until the dashboard
+/// closes. Anything sent on `refresh` triggers an early read. With the event
+/// tracker live this is just a cheap copy of its state.
+fn spawn_poller(
+    services: Vec<Service>,
+    legacy: bool,
+    tx: Sender<Vec<ServiceStatus>>,
+    refresh: Receiver<()>,
+) {
+    thread::spawn(move || loop {
+        if tx.send(current_statuses(&services, legacy)).is_err() {
+            break;
+        }
+        match refresh.recv_timeout(REFRESH_INTERVAL) {
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    });
+}
+
+/// Runs a compose command without letting its output reach the dashboard.
+fn spawn_action(
+    service: Service,
+    args: &'static [&'static str],
+    legacy: bool,
+    tx: Sender<OpResult>,
+) {
+    thread::spawn(move || {
+        let result = run_docker_compose(&service, args, legacy);
+        refresh_statuses(std::slice::from_ref(&service));
+        let _ = tx.send(result);
+    });
+}
+
+struct Dashboard<'a> {
+    services: &'a [Service],
+    statuses: Vec<ServiceStatus>,
+    refreshed: Option<Instant>,
+    cursor: usize,
+    /// Service name -> action label while its command runs
+    pending: HashMap<String, &'static str>,
+    message: String,
+}
+
+impl Dashboard<'_> {
+    fn draw(&self) {
+        let mut out = stdout().lock();
+        let _ = execute!(out, MoveTo(0, 0), Clear(ClearType::All));
+
+        let refreshed = match (self.refreshed, tracker_version()) {
+            (None, _) => "loading...".to_string(),
+            (Some(_), Some(_)) => "live, following docker events".to_string(),
+            (Some(at), None) => format!(
+                "refreshed {}s ago, every {}s",
+                at.elapsed().as_secs(),
+                REFRESH_INTERVAL.as_secs()
+            ),
+        };
+        let _ = write!(
+            out,
+            "\r\n{}  {}\r\n\r\n",
+            "Live Dashboard".bold().cyan(),
+            format!("({})", refreshed).bright_black()
+        );
+
+        let width = self
+            .services
+            .iter()
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("NAME".len());
+        let _ = write!(
+            out,
+            "  {}\r\n",
+            format!(
+                "{:<width$}  {:<10}  {:<10}  PORTS",
+                "NAME",
+                "STATE",
+                "CONTAINERS",
+                width = width
+            )
+            .bright_black()
+        );
+
+        for (i, service) in self.services.iter().enumerate() {
+            let marker = if i == self.cursor { ">" } else { " " };
+            let name = format!("{:<width$}", service.name, width = width);
+            let name = if i == self.cursor {
+                name.bold().cyan()
+            } else {
+                name.cyan()
+            };
+
+            let status = self.statuses.iter().find(|s| s.name == service.name);
+            let (state, containers, ports) = match status {
+                Some(status) => {
+                    let ports: Vec<&str> = status
+                        .containers
+                        .iter()
+                        .flat_map(|c| c.ports.iter().map(|p| p.as_str()))
+                        .collect();
+                    (
+                        format!("{:<10}", status.state.label())
+                            .color(state_color(status.state))
+                            .to_string(),
+                        format!("{}/{}", status.running_count(), status.containers.len()),
+                        ports.join(", "),
+                    )
+                }
+                None => (format!("{:<10}", "?"), String::new(), String::new()),
+            };
+            let state = match self.pending.get(&service.name) {
+                Some(label) => format!("{:<10}", label).yellow().to_string(),
+                None => state,
+            };
+
+            let _ = write!(
+                out,
+                "{} {}  {}  {:<10}  {}\r\n",
+                marker.cyan(),
+                name,
+                state,
+                containers,
+                ports.bright_black()
+            );
+        }
+
+        let _ = write!(out, "\r\n{}\r\n", self.message);
+        let _ = write!(
+            out,
+            "{}",
+            "↑↓ select  s start  p stop  r restart  f refresh  q back".bright_black()
+        );
+        let _ = out.flush();
+    }
+}
+
+pub fn show_dashboard(services: &[Service], config: &Config) {
+    let legacy = config.legacy_compose;
+    let (status_tx, status_rx) = mpsc::channel();
+    let (refresh_tx, refresh_rx) = mpsc::channel();
+    let (action_tx, action_rx) = mpsc::channel();
+    spawn_poller(services.to_vec(), legacy, status_tx, refresh_rx);
+
+    let mut dashboard = Dashboard {
+        services,
+        statuses: Vec::new(),
+        refreshed: None,
+        cursor: 0,
+        pending: HashMap::new(),
+        message: String::new(),
+    };
+
+    let _ = execute!(stdout(), Hide);
+    let _ = enable_raw_mode();
+    dashboard.draw();
+    let mut last_draw = Instant::now();
+    let mut last_version = tracker_version();
+
+    loop {
+        let mut dirty = false;
+
+        if let Ok(true) = poll(Duration::from_millis(100)) {
+            if let Ok(Event::Key(key)) = read() {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                let action: Option<(&'static str, &'static [&'static str])> = match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    _ if ctrl_c => break,
+                    KeyCode::Up => {
+                        dashboard.cursor = dashboard
+                            .cursor
+                            .checked_sub(1)
+                            .unwrap_or(services.len() - 1);
+                        None
+                    }
+                    KeyCode::Down => {
+                        dashboard.cursor = (dashboard.cursor + 1) % services.len();
+                        None
+                    }
+                    KeyCode::Char('f') => {
+                        let _ = refresh_tx.send(());
+                        None
+                    }
+                    KeyCode::Char('s') => Some(("starting", &["up", "-d"])),
+                    KeyCode::Char('p') => Some(("stopping", &["down"])),
+                    KeyCode::Char('r') => Some(("restarting", &["restart"])),
+                    _ => None,
+                };
+
+                if let Some((label, args)) = action {
+                    let service = &services[dashboard.cursor];
+                    if !dashboard.pending.contains_key(&service.name) {
+                        dashboard.pending.insert(service.name.clone(), label);
+                        spawn_action(service.clone(), args, legacy, action_tx.clone());
+                    }
+                }
+                dirty = true;
+            }
+        }
+
+        while let Ok(result) = action_rx.try_recv() {
+            let name = &result.name;
+            let label = dashboard.pending.remove(name).unwrap_or("action");
+            dashboard.message = if result.ok {
+                format!("{}: {} {}", name.cyan(), label, "OK".green())
+            } else {
+                // Only room for one line here; the last one usually says why
+                let reason = result.stderr.lines().last().unwrap_or_default();
+                format!(
+                    "{}: {} {} {}",
+                    name.cyan(),
+                    label,
+                    "ERROR".red(),
+                    reason.bright_black()
+                )
+            };
+            let _ = refresh_tx.send(());
+            dirty = true;
+        }
+
+        let version = tracker_version();
+        if version != last_version {
+            last_version = version;
+            let _ = refresh_tx.send(());
+        }
+
+        while let Ok(statuses) = status_rx.try_recv() {
+            dashboard.statuses = statuses;
+            dashboard.refreshed = Some(Instant::now());
+            dirty = true;
+        }
+
+        // Redraw at least once a second so the "refreshed" age stays current
+        if dirty || last_draw.elapsed() >= Duration::from_secs(1) {
+            dashboard.draw();
+            last_draw = Instant::now();
+        }
+    }
+
+    let _ = disable_raw_mode();
+    let _ = execute!(stdout(), Show);
+}
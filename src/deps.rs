@@ -0,0 +1,240 @@
+use crate::config::Config;
+use crate::service::Service;
+use std::collections::{HashMap, HashSet};
+
+/// Compose files can declare dependencies on other stacks under this key:
+///
+/// ```yaml
+/// x-dockerstrator:
+///   depends_on: [postgres, rabbitmq]
+/// ```
+const COMPOSE_EXTENSION_KEY: &str = "x-dockerstrator";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Dependencies first
+    Start,
+    /// Dependents first
+    Stop,
+}
+
+/// Which discovered stacks each stack needs running first, merged from the
+/// `[dependencies]` config table and the compose extension key. Names that
+/// don't match a discovered stack are ignored.
+pub struct DependencyGraph {
+    edges: HashMap<String, Vec<String>>,
+}
+
+fn compose_dependencies(service: &Service) -> Vec<String> {
+    service
+        .load_compose()
+        .and_then(|compose| compose.get(COMPOSE_EXTENSION_KEY).cloned())
+        .and_then(|ext| ext.get("depends_on").cloned())
+        .and_then(|deps| deps.as_sequence().cloned())
+        .map(|deps| {
+            deps.iter()
+                .filter_map(|d| d.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl DependencyGraph {
+    pub fn build(services: &[Service], config: &Config) -> DependencyGraph {
+        let known: HashSet<&str> = services.iter().map(|s| s.name.as_str()).collect();
+        let mut edges = HashMap::new();
+
+        for service in services {
+            let mut deps: Vec<String> = Vec::new();
+            let declared = config
+                .dependencies
+                .get(&service.name)
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .chain(compose_dependencies(service));
+            for dep in declared {
+                if known.contains(dep.as_str()) && !deps.contains(&dep) {
+                    deps.push(dep);
+                }
+            }
+            edges.insert(service.name.clone(), deps);
+        }
+        DependencyGraph { edges }
+    }
+
+    /// Stacks `name` declares directly.
+    pub fn dependencies(&self, name: &str) -> &[String] {
+        self.edges
+            .get(name)
+            .map(|d| d.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Everything `name` needs, directly or through other stacks.
+    pub fn requires(&self, name: &str) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut stack: Vec<&str> = self.dependencies(name).iter().map(|d| d.as_str()).collect();
+        while let Some(next) = stack.pop() {
+            if seen.insert(next.to_string()) {
+                stack.extend(self.dependencies(next).iter().map(|d| d.as_str()));
+            }
+        }
+        seen
+    }
+
+    /// A dependency loop through `start`, e.g. ["api", "db", "api"].
+    fn find_cycle(&self, start: &str) -> Option<Vec<String>> {
+        let mut path = vec![start.to_string()];
+        let mut visited = HashSet::new();
+        if self.walk(start, start, &mut path, &mut visited) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    fn walk(
+        &self,
+        target: &str,
+        current: &str,
+        path: &mut Vec<String>,
+        visited: &mut HashSet<String>,
+    ) -> bool {
+        for dep in self.dependencies(current) {
+            path.push(dep.clone());
+            if dep == target {
+                return true;
+            }
+            if visited.insert(dep.clone()) && self.walk(target, dep, path, visited) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+
+    /// Splits `selected` into waves that can each run in parallel: for start,
+    /// a stack comes after every selected stack it requires (even through
+    /// unselected ones); stop runs the same waves in reverse.
+    pub fn waves(
+        &self,
+        selected: &[Service],
+        direction: Direction,
+    ) -> Result<Vec<Vec<Service>>, String> {
+        let names: HashSet<&str> = selected.iter().map(|s| s.name.as_str()).collect();
+        let requires: HashMap<&str, HashSet<String>> = selected
+            .iter()
+            .map(|s| (s.name.as_str(), self.requires(&s.name)))
+            .collect();
+
+        if let Some(service) = selected
+            .iter()
+            .find(|s| requires[s.name.as_str()].contains(&s.name))
+        {
+            let cycle = self
+                .find_cycle(&service.name)
+                .unwrap_or_else(|| vec![service.name.clone(), service.name.clone()]);
+            return Err(format!("Dependency cycle: {}", cycle.join(" -> ")));
+        }
+
+        let mut placed: HashSet<String> = HashSet::new();
+        let mut remaining: Vec<&Service> = selected.iter().collect();
+        let mut waves = Vec::new();
+        while !remaining.is_empty() {
+            let (ready, blocked): (Vec<&Service>, Vec<&Service>) =
+                remaining.into_iter().partition(|s| {
+                    requires[s.name.as_str()]
+                        .iter()
+                        .all(|dep| placed.contains(dep) || !names.contains(dep.as_str()))
+                });
+            placed.extend(ready.iter().map(|s| s.name.clone()));
+            waves.push(ready.into_iter().cloned().collect::<Vec<Service>>());
+            remaining = blocked;
+        }
+
+        if direction == Direction::Stop {
+            waves.reverse();
+        }
+        Ok(waves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn service(name: &str) -> Service {
+        Service {
+            name: name.to_string(),
+            path: PathBuf::from("/nonexistent").join(name),
+            compose_file: "docker-compose.yml".to_string(),
+        }
+    }
+
+    fn graph(services: &[Service], deps: &[(&str, &[&str])]) -> DependencyGraph {
+        let mut config = Config::default();
+        for (name, needs) in deps {
+            let needs = needs.iter().map(|n| n.to_string()).collect();
+            config.dependencies.insert(name.to_string(), needs);
+        }
+        DependencyGraph::build(services, &config)
+    }
+
+    fn names(waves: &[Vec<Service>]) -> Vec<Vec<&str>> {
+        waves
+            .iter()
+            .map(|wave| wave.iter().map(|s| s.name.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn starts_dependencies_first() {
+        let services = vec![service("api"), service("db"), service("web")];
+        let graph = graph(&services, &[("api", &["db"]), ("web", &["api"])]);
+        let waves = graph.waves(&services, Direction::Start).unwrap();
+        assert_eq!(names(&waves), vec![vec!["db"], vec!["api"], vec!["web"]]);
+    }
+
+    #[test]
+    fn stops_dependents_first() {
+        let services = vec![service("api"), service("db"), service("cache")];
+        let graph = graph(&services, &[("api", &["db", "cache"])]);
+        let waves = graph.waves(&services, Direction::Stop).unwrap();
+        assert_eq!(names(&waves), vec![vec!["api"], vec!["db", "cache"]]);
+    }
+
+    #[test]
+    fn orders_through_unselected_stacks() {
+        let services = vec![service("web"), service("api"), service("db")];
+        let graph = graph(&services, &[("web", &["api"]), ("api", &["db"])]);
+        let selected = vec![service("web"), service("db")];
+        let waves = graph.waves(&selected, Direction::Start).unwrap();
+        assert_eq!(names(&waves), vec![vec!["db"], vec!["web"]]);
+    }
+
+    #[test]
+    fn ignores_unknown_and_duplicate_dependencies() {
+        let services = vec![service("api"), service("db")];
+        let graph = graph(&services, &[("api", &["db", "db", "missing"])]);
+        assert_eq!(graph.dependencies("api"), ["db".to_string()]);
+        assert!(graph.dependencies("db").is_empty());
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let services = vec![service("a"), service("b"), service("c")];
+        let graph = graph(&services, &[("a", &["b"]), ("b", &["c"]), ("c", &["a"])]);
+        let err = graph.waves(&services, Direction::Start).unwrap_err();
+        assert_eq!(err, "Dependency cycle: a -> b -> c -> a");
+    }
+
+    #[test]
+    fn reports_self_dependency() {
+        let services = vec![service("a")];
+        let graph = graph(&services, &[("a", &["a"])]);
+        let err = graph.waves(&services, Direction::Stop).unwrap_err();
+        assert_eq!(err, "Dependency cycle: a -> a");
+    }
+}
@@ -1,6 +1,211 @@
+use crate::config::Config;
 use crate::service::Service;
-use std::process::Command;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// Most docker commands `run_parallel` and `check_all_statuses` run at once; 0 for no limit.
+static MAX_PARALLEL: AtomicUsize = AtomicUsize::new(0);
+/// Seconds before a docker command is killed; 0 to wait forever.
+static OPERATION_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+/// Read-only queries (status, images, stats) never legitimately take long,
+/// so they're bounded even when operations aren't.
+const QUERY_TIMEOUT: u64 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Up,
+    Partial,
+    Unhealthy,
+    Exited,
+    Down,
+    /// `docker compose ps` itself failed
+    Unknown,
+}
+
+impl State {
+    pub fn label(&self) -> &'static str {
+        match self {
+            State::Up => "UP",
+            State::Partial => "PARTIAL",
+            State::Unhealthy => "UNHEALTHY",
+            State::Exited => "EXITED",
+            State::Down => "DOWN",
+            State::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ContainerStatus {
+    pub name: String,
+    pub service: String,
+    pub state: String,
+    pub health: String,
+    pub exit_code: i64,
+    pub ports: Vec<String>,
+    pub uptime: String,
+}
+
+impl ContainerStatus {
+    pub fn is_running(&self) -> bool {
+        self.state == "running"
+    }
+
+    /// Running and not failing its healthcheck, or a one-off container that
+    /// finished cleanly (migrations, init jobs).
+    pub fn is_ok(&self) -> bool {
+        if self.is_running() {
+            self.health != "unhealthy"
+        } else {
+            self.state == "exited" && self.exit_code == 0
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub path: PathBuf,
+    pub compose_file: String,
+    pub state: State,
+    pub running: bool,
+    /// Number of containers, kept as `containers` for scripts reading the count
+    #[serde(rename = "containers")]
+    pub container_count: usize,
+    #[serde(rename = "container_details")]
+    pub containers: Vec<ContainerStatus>,
+    /// Why the status couldn't be queried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ServiceStatus {
+    fn new(service: &Service, containers: Vec<ContainerStatus>) -> Self {
+        let running_count = containers.iter().filter(|c| c.is_running()).count();
+        let state = if containers.is_empty() {
+            State::Down
+        } else if running_count == 0 {
+            State::Exited
+        } else if containers
+            .iter()
+            .any(|c| c.is_running() && c.health == "unhealthy")
+        {
+            State::Unhealthy
+        } else if containers.iter().any(|c| !c.is_ok()) {
+            State::Partial
+        } else {
+            State::Up
+        };
+
+        ServiceStatus {
+            name: service.name.clone(),
+            path: service.path.clone(),
+            compose_file: service.compose_file.clone(),
+            state,
+            running: running_count > 0,
+            container_count: containers.len(),
+            containers,
+            error: None,
+        }
+    }
+
+    fn failed(service: &Service, error: String) -> Self {
+        ServiceStatus {
+            state: State::Unknown,
+            error: Some(error),
+            ..ServiceStatus::new(service, Vec::new())
+        }
+    }
+
+    pub fn running_count(&self) -> usize {
+        self.containers.iter().filter(|c| c.is_running()).count()
+    }
+}
+
+/// One entry of `docker compose ps --format json`.
+#[derive(Deserialize)]
+struct PsEntry {
+    #[serde(rename = "Name", default)]
+    name: String,
+    #[serde(rename = "Service", default)]
+    service: String,
+    #[serde(rename = "State", default)]
+    state: String,
+    #[serde(rename = "Health", default)]
+    health: String,
+    #[serde(rename = "ExitCode", default)]
+    exit_code: i64,
+    #[serde(rename = "Status", default)]
+    status: String,
+    #[serde(rename = "Publishers", default)]
+    publishers: Option<Vec<PsPublisher>>,
+}
+
+#[derive(Deserialize)]
+struct PsPublisher {
+    #[serde(rename = "TargetPort", default)]
+    target_port: u16,
+    #[serde(rename = "PublishedPort", default)]
+    published_port: u16,
+    #[serde(rename = "Protocol", default)]
+    protocol: String,
+}
+
+impl From<PsEntry> for ContainerStatus {
+    fn from(entry: PsEntry) -> Self {
+        let mut ports: Vec<String> = Vec::new();
+        for p in entry.publishers.unwrap_or_default() {
+            if p.published_port == 0 {
+                continue;
+            }
+            let port = format!("{}->{}/{}", p.published_port, p.target_port, p.protocol);
+            // IPv4 and IPv6 bindings show up as separate publishers
+            if !ports.contains(&port) {
+                ports.push(port);
+            }
+        }
+
+        let uptime = entry
+            .status
+            .strip_prefix("Up ")
+            .map(|rest| rest.split(" (").next().unwrap_or(rest).to_string())
+            .unwrap_or_default();
+
+        ContainerStatus {
+            name: entry.name,
+            service: entry.service,
+            state: entry.state.to_lowercase(),
+            health: entry.health.to_lowercase(),
+            exit_code: entry.exit_code,
+            ports,
+            uptime,
+        }
+    }
+}
+
+/// Older compose releases print a single JSON array, newer ones one object per line.
+fn parse_ps_output(stdout: &str) -> Vec<ContainerStatus> {
+    let trimmed = stdout.trim();
+    if trimmed.starts_with('[') {
+        return serde_json::from_str::<Vec<PsEntry>>(trimmed)
+            .map(|entries| entries.into_iter().map(ContainerStatus::from).collect())
+            .unwrap_or_default();
+    }
+
+    trimmed
+        .lines()
+        .filter_map(|line| serde_json::from_str::<PsEntry>(line).ok())
+        .map(ContainerStatus::from)
+        .collect()
+}
 
 pub fn build_compose_cmd(service: &Service, legacy: bool) -> Command {
     let mut cmd = if legacy {
@@ -16,59 +221,432 @@ pub fn build_compose_cmd(service: &Service, legacy: bool) -> Command {
     cmd
 }
 
-pub fn run_docker_compose(service: &Service, args: &[&str], legacy: bool) -> bool {
-    build_compose_cmd(service, legacy)
-        .args(args)
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false)
+/// Applies the config's concurrency and timeout limits to docker commands
+/// started from here on.
+pub fn apply_limits(config: &Config) {
+    MAX_PARALLEL.store(config.max_parallel, Ordering::SeqCst);
+    OPERATION_TIMEOUT.store(config.operation_timeout, Ordering::SeqCst);
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+pub fn operation_timeout() -> u64 {
+    OPERATION_TIMEOUT.load(Ordering::SeqCst)
+}
+
+pub fn query_timeout() -> u64 {
+    match operation_timeout() {
+        0 => QUERY_TIMEOUT,
+        secs => secs.min(QUERY_TIMEOUT),
+    }
+}
+
+/// Starts `cmd` in a process group of its own, so [`kill_process_group`] also
+/// reaches the compose plugin process `docker` runs it in.
+pub fn own_process_group(cmd: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    cmd
+}
+
+/// Kills a child started with [`own_process_group`] and everything in its group.
+pub fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+pub fn timed_out_message(timeout: u64) -> String {
+    format!("timed out after {}s and was killed", timeout)
+}
+
+/// `Command::output` that kills the process after `timeout` seconds (0 for
+/// never), so a hung daemon can't block its caller forever.
+pub fn output_with_timeout(cmd: &mut Command, timeout: u64) -> io::Result<Output> {
+    cmd.stdin(Stdio::null());
+    if timeout == 0 {
+        return cmd.output();
+    }
+
+    let mut child = own_process_group(cmd)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            kill_process_group(&mut child);
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                timed_out_message(timeout),
+            ));
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Runs `job` on every item on a pool of at most `MAX_PARALLEL` threads,
+/// sending back each item's index with its result as it finishes.
+pub fn spawn_bounded<T, R, F>(items: Vec<T>, job: F) -> Receiver<(usize, R)>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    let workers = match MAX_PARALLEL.load(Ordering::SeqCst) {
+        0 => items.len(),
+        max => max.min(items.len()),
+    };
+    let queue = Arc::new(Mutex::new(items.into_iter().enumerate()));
+    let job = Arc::new(job);
+    let (tx, rx) = mpsc::channel();
+
+    for _ in 0..workers {
+        let queue = Arc::clone(&queue);
+        let job = Arc::clone(&job);
+        let tx = tx.clone();
+        thread::spawn(move || loop {
+            let next = queue.lock().ok().and_then(|mut queue| queue.next());
+            let (index, item) = match next {
+                Some(next) => next,
+                None => break,
+            };
+            if tx.send((index, job(item))).is_err() {
+                break;
+            }
+        });
+    }
+    rx
 }
 
-pub fn get_service_status(service: &Service, legacy: bool) -> bool {
-    let output = build_compose_cmd(service, legacy)
-        .args(&["ps", "-q"])
-        .output();
-    match output {
-        Ok(out) => !out.stdout.is_empty(),
-        Err(_) => false,
+/// [`spawn_bounded`] that waits for every item, returning the results in the
+/// order the items were given; None where a job panicked.
+pub fn run_bounded<T, R, F>(items: Vec<T>, job: F) -> Vec<Option<R>>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+    for (index, result) in spawn_bounded(items, job) {
+        results[index] = Some(result);
     }
+    results
+}
+
+/// Outcome of one compose command on one service, with everything it printed.
+#[derive(Clone, Debug)]
+pub struct OpResult {
+    pub name: String,
+    pub ok: bool,
+    pub stdout: String,
+    pub stderr: String,
 }
 
-pub fn check_all_statuses(services: &[Service], legacy: bool) -> Vec<(String, bool)> {
-    let handles: Vec<_> = services
-        .iter()
-        .map(|service| {
-            let service = service.clone();
-            thread::spawn(move || {
-                let up = get_service_status(&service, legacy);
-                (service.name, up)
-            })
+impl OpResult {
+    pub fn failed(name: &str, error: &str) -> OpResult {
+        OpResult {
+            name: name.to_string(),
+            ok: false,
+            stdout: String::new(),
+            stderr: error.to_string(),
+        }
+    }
+}
+
+/// Runs a compose command with its output captured instead of sent to the terminal.
+pub fn run_docker_compose(service: &Service, args: &[&str], legacy: bool) -> OpResult {
+    match output_with_timeout(
+        build_compose_cmd(service, legacy).args(args),
+        operation_timeout(),
+    ) {
+        Ok(out) => OpResult {
+            name: service.name.clone(),
+            ok: out.status.success(),
+            stdout: String::from_utf8_lossy(&out.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&out.stderr).to_string(),
+        },
+        Err(e) => OpResult::failed(&service.name, &e.to_string()),
+    }
+}
+
+/// Stdout of a compose query, or what went wrong running it.
+fn query_output(service: &Service, args: &[&str], legacy: bool) -> Result<String, String> {
+    let out = output_with_timeout(
+        build_compose_cmd(service, legacy).args(args),
+        query_timeout(),
+    )
+    .map_err(|e| e.to_string())?;
+    if out.status.success() {
+        Ok(String::from_utf8_lossy(&out.stdout).to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        Err(if stderr.is_empty() {
+            out.status.to_string()
+        } else {
+            stderr
         })
-        .collect();
+    }
+}
+
+pub fn get_service_status(service: &Service, legacy: bool) -> ServiceStatus {
+    // docker-compose v1 has no JSON output; count container IDs instead
+    if legacy {
+        return match query_output(service, &["ps", "-q"], legacy) {
+            Ok(stdout) => {
+                let containers = stdout
+                    .lines()
+                    .filter(|l| !l.trim().is_empty())
+                    .map(|id| ContainerStatus {
+                        name: id.trim().to_string(),
+                        service: String::new(),
+                        state: "running".to_string(),
+                        health: String::new(),
+                        exit_code: 0,
+                        ports: Vec::new(),
+                        uptime: String::new(),
+                    })
+                    .collect();
+                ServiceStatus::new(service, containers)
+            }
+            Err(e) => ServiceStatus::failed(service, e),
+        };
+    }
+
+    match query_output(service, &["ps", "-a", "--format", "json"], legacy) {
+        Ok(stdout) => ServiceStatus::new(service, parse_ps_output(&stdout)),
+        Err(e) => ServiceStatus::failed(service, e),
+    }
+}
+
+/// Compose service names defined in the stack's compose file.
+pub fn get_compose_services(service: &Service, legacy: bool) -> Vec<String> {
+    output_with_timeout(
+        build_compose_cmd(service, legacy).args(["config", "--services"]),
+        query_timeout(),
+    )
+    .map(|out| {
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect()
+    })
+    .unwrap_or_default()
+}
 
-    handles
+pub fn check_all_statuses(services: &[Service], legacy: bool) -> Vec<ServiceStatus> {
+    let rx = spawn_bounded(services.to_vec(), move |service| {
+        get_service_status(&service, legacy)
+    });
+
+    let mut statuses: Vec<Option<ServiceStatus>> = vec![None; services.len()];
+    for (index, status) in rx {
+        statuses[index] = Some(status);
+    }
+    statuses
         .into_iter()
-        .map(|h| h.join().unwrap_or_else(|_| ("?".to_string(), false)))
+        .zip(services)
+        .map(|(status, service)| status.unwrap_or_else(|| ServiceStatus::new(service, Vec::new())))
         .collect()
 }
 
-pub fn run_parallel(services: Vec<Service>, args: &[&str], legacy: bool) -> Vec<(String, bool)> {
+/// How often [`run_parallel`] reports [`Progress::Tick`].
+const PROGRESS_TICK: Duration = Duration::from_millis(100);
+
+/// What [`run_parallel`] reports while its commands run.
+pub enum Progress<'a> {
+    /// Nothing finished during the last tick; lets callers animate
+    Tick,
+    Done(&'a OpResult),
+}
+
+/// Runs the same compose command on every service concurrently, reporting each
+/// one as it finishes. Results come back in the order the services were given.
+pub fn run_parallel(
+    services: Vec<Service>,
+    args: &[&str],
+    legacy: bool,
+    mut on_progress: impl FnMut(Progress),
+) -> Vec<OpResult> {
     let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+    let rx = spawn_bounded(services.clone(), move |service| {
+        let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        run_docker_compose(&service, &args_ref, legacy)
+    });
+
+    let mut results: Vec<Option<OpResult>> = vec![None; services.len()];
+    loop {
+        match rx.recv_timeout(PROGRESS_TICK) {
+            Ok((index, result)) => {
+                on_progress(Progress::Done(&result));
+                results[index] = Some(result);
+            }
+            Err(RecvTimeoutError::Timeout) => on_progress(Progress::Tick),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
 
-    let handles: Vec<_> = services
+    results
         .into_iter()
-        .map(|service| {
-            let args = args.clone();
-            thread::spawn(move || {
-                let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
-                let ok = run_docker_compose(&service, &args_ref, legacy);
-                (service.name, ok)
-            })
+        .zip(&services)
+        .map(|(result, service)| {
+            result.unwrap_or_else(|| OpResult::failed(&service.name, "operation panicked"))
         })
-        .collect();
+        .collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BuildOptions {
+    pub no_cache: bool,
+    pub pull: bool,
+}
+
+impl BuildOptions {
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["build".to_string()];
+        if self.no_cache {
+            args.push("--no-cache".to_string());
+        }
+        if self.pull {
+            args.push("--pull".to_string());
+        }
+        args
+    }
+}
+
+pub struct PullResult {
+    pub result: OpResult,
+    /// Image references whose local image ID changed during the pull
+    pub updated: Vec<String>,
+}
+
+fn get_image_ids(service: &Service, legacy: bool) -> Vec<(String, Option<String>)> {
+    let images = match output_with_timeout(
+        build_compose_cmd(service, legacy).args(["config", "--images"]),
+        query_timeout(),
+    ) {
+        Ok(out) => String::from_utf8_lossy(&out.stdout).to_string(),
+        Err(_) => return Vec::new(),
+    };
+
+    images
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|image| {
+            let id = output_with_timeout(
+                Command::new("docker").args(["image", "inspect", "--format", "{{.Id}}", image]),
+                query_timeout(),
+            )
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string());
+            (image.to_string(), id)
+        })
+        .collect()
+}
 
-    handles
+fn collect_image_ids(services: &[Service], legacy: bool) -> Vec<Vec<(String, Option<String>)>> {
+    run_bounded(services.to_vec(), move |service| {
+        get_image_ids(&service, legacy)
+    })
+    .into_iter()
+    .map(Option::unwrap_or_default)
+    .collect()
+}
+
+/// Runs `compose pull` on every service and reports which images actually changed.
+pub fn pull_images(
+    services: Vec<Service>,
+    legacy: bool,
+    on_progress: impl FnMut(Progress),
+) -> Vec<PullResult> {
+    let before = collect_image_ids(&services, legacy);
+    let results = run_parallel(services.clone(), &["pull"], legacy, on_progress);
+    let after = collect_image_ids(&services, legacy);
+
+    results
         .into_iter()
-        .map(|h| h.join().unwrap_or_else(|_| ("?".to_string(), false)))
+        .zip(before.into_iter().zip(after))
+        .map(|(result, (before, after))| {
+            let updated = after
+                .into_iter()
+                .filter(|(image, id)| {
+                    id.is_some() && !before.iter().any(|(i, old)| i == image && old == id)
+                })
+                .map(|(image, _)| image)
+                .collect();
+            PullResult { result, updated }
+        })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY: &str = r#"{"Name":"shop-api-1","Service":"api","State":"running","Health":"healthy","ExitCode":0,"Status":"Up 5 minutes (healthy)","Publishers":[{"URL":"0.0.0.0","TargetPort":80,"PublishedPort":8080,"Protocol":"tcp"},{"URL":"::","TargetPort":80,"PublishedPort":8080,"Protocol":"tcp"},{"URL":"","TargetPort":9000,"PublishedPort":0,"Protocol":"tcp"}]}"#;
+
+    #[test]
+    fn parses_one_object_per_line() {
+        let exited = r#"{"Name":"shop-worker-1","Service":"worker","State":"exited","ExitCode":137,"Status":"Exited (137) 2 hours ago"}"#;
+        let containers = parse_ps_output(&format!("{}\n{}\n", ENTRY, exited));
+        assert_eq!(containers.len(), 2);
+
+        let api = &containers[0];
+        assert_eq!(api.name, "shop-api-1");
+        assert_eq!(api.service, "api");
+        assert_eq!(api.state, "running");
+        assert_eq!(api.health, "healthy");
+        assert_eq!(api.ports, ["8080->80/tcp"]);
+        assert_eq!(api.uptime, "5 minutes");
+
+        let worker = &containers[1];
+        assert_eq!(worker.state, "exited");
+        assert_eq!(worker.exit_code, 137);
+        assert!(worker.ports.is_empty());
+        assert!(worker.uptime.is_empty());
+    }
+
+    #[test]
+    fn parses_json_array() {
+        let containers = parse_ps_output(&format!("[{}]", ENTRY));
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].name, "shop-api-1");
+    }
+
+    #[test]
+    fn skips_unparseable_output() {
+        assert!(parse_ps_output("").is_empty());
+        assert!(parse_ps_output("[not json").is_empty());
+        let containers = parse_ps_output(&format!("warning: something\n{}", ENTRY));
+        assert_eq!(containers.len(), 1);
+    }
+}
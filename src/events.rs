@@ -0,0 +1,243 @@
+use crate::docker::{check_all_statuses, ServiceStatus};
+use crate::service::Service;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+/// Events for one stack usually come in bursts (create, start, health...);
+/// wait this long after the first one before re-reading its status.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+static TRACKER: OnceLock<StatusTracker> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct DockerEvent {
+    #[serde(rename = "Actor", default)]
+    actor: EventActor,
+}
+
+#[derive(Deserialize, Default)]
+struct EventActor {
+    #[serde(rename = "Attributes", default)]
+    attributes: HashMap<String, String>,
+}
+
+/// In-memory status of every discovered service, kept current by a single
+/// `docker events` subscription instead of polling each stack.
+pub struct StatusTracker {
+    services: Vec<Service>,
+    statuses: Mutex<HashMap<String, ServiceStatus>>,
+    ready: AtomicBool,
+    live: AtomicBool,
+    /// Whether status reads use docker-compose v1
+    legacy: AtomicBool,
+    version: AtomicU64,
+    child: Mutex<Option<Child>>,
+}
+
+impl StatusTracker {
+    fn snapshot(&self) -> Option<Vec<ServiceStatus>> {
+        if !self.is_live() {
+            return None;
+        }
+        let statuses = self.statuses.lock().ok()?;
+        self.services
+            .iter()
+            .map(|s| statuses.get(&s.name).cloned())
+            .collect()
+    }
+
+    fn is_live(&self) -> bool {
+        self.ready.load(Ordering::SeqCst) && self.live.load(Ordering::SeqCst)
+    }
+
+    fn update(&self, statuses: Vec<ServiceStatus>) {
+        if let Ok(mut map) = self.statuses.lock() {
+            for status in statuses {
+                map.insert(status.name.clone(), status);
+            }
+        }
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Docker's default project name for a directory: lowercase, only [a-z0-9_-].
+fn default_project_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect()
+}
+
+/// Maps the compose labels on an event back to one of the discovered services.
+fn match_service(services: &[Service], attributes: &HashMap<String, String>) -> Option<usize> {
+    if let Some(dir) = attributes.get("com.docker.compose.project.working_dir") {
+        let dir = PathBuf::from(dir);
+        let canonical = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+        let found = services.iter().position(|s| {
+            s.path == dir
+                || s.path
+                    .canonicalize()
+                    .map(|p| p == canonical)
+                    .unwrap_or(false)
+        });
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    let project = attributes.get("com.docker.compose.project")?;
+    services
+        .iter()
+        .position(|s| &default_project_name(&s.path) == project)
+}
+
+fn spawn_events() -> Option<Child> {
+    Command::new("docker")
+        .args([
+            "events",
+            "--format",
+            "{{json .}}",
+            "--filter",
+            "type=container",
+            "--filter",
+            "label=com.docker.compose.project",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()
+}
+
+/// Takes the initial full status read, then re-reads the status of stacks
+/// that had events, batching bursts together.
+fn run_refresher(tracker: &'static StatusTracker, rx: Receiver<usize>) {
+    let legacy = tracker.legacy.load(Ordering::SeqCst);
+    tracker.update(check_all_statuses(&tracker.services, legacy));
+    tracker.ready.store(true, Ordering::SeqCst);
+
+    while let Ok(first) = rx.recv() {
+        thread::sleep(DEBOUNCE);
+        let mut changed = HashSet::from([first]);
+        changed.extend(rx.try_iter());
+
+        let services: Vec<Service> = changed
+            .into_iter()
+            .map(|i| tracker.services[i].clone())
+            .collect();
+        let legacy = tracker.legacy.load(Ordering::SeqCst);
+        tracker.update(check_all_statuses(&services, legacy));
+    }
+}
+
+/// Starts tracking in the background. The first full status read and the
+/// subscription happen off the calling thread; until both are up, callers of
+/// [`current_statuses`] transparently fall back to polling.
+pub fn start_tracker(services: &[Service], legacy: bool) {
+    let tracker = TRACKER.get_or_init(|| StatusTracker {
+        services: services.to_vec(),
+        statuses: Mutex::new(HashMap::new()),
+        ready: AtomicBool::new(false),
+        live: AtomicBool::new(false),
+        legacy: AtomicBool::new(legacy),
+        version: AtomicU64::new(0),
+        child: Mutex::new(None),
+    });
+
+    let mut child = match spawn_events() {
+        Some(child) => child,
+        None => return,
+    };
+    let stdout = match child.stdout.take() {
+        Some(stdout) => stdout,
+        None => return,
+    };
+    if let Ok(mut slot) = tracker.child.lock() {
+        *slot = Some(child);
+    }
+    tracker.live.store(true, Ordering::SeqCst);
+
+    // Subscribed before the initial read, so events in between are queued, not missed
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || run_refresher(tracker, rx));
+
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let event: DockerEvent = match serde_json::from_str(&line) {
+                Ok(event) => event,
+                Err(_) => continue,
+            };
+            if let Some(index) = match_service(&tracker.services, &event.actor.attributes) {
+                if tx.send(index).is_err() {
+                    break;
+                }
+            }
+        }
+        // Daemon went away or the subscription ended; go back to polling
+        tracker.live.store(false, Ordering::SeqCst);
+    });
+}
+
+pub fn stop_tracker() {
+    if let Some(tracker) = TRACKER.get() {
+        if let Ok(mut slot) = tracker.child.lock() {
+            if let Some(mut child) = slot.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+/// Re-reads `services` right away, so whatever runs after an operation sees
+/// its outcome instead of waiting for the debounced events.
+pub fn refresh_statuses(services: &[Service]) {
+    if let Some(tracker) = TRACKER.get().filter(|t| t.is_live()) {
+        let legacy = tracker.legacy.load(Ordering::SeqCst);
+        tracker.update(check_all_statuses(services, legacy));
+    }
+}
+
+/// Switches the tracker to the docker command Settings picked, re-reading
+/// every stack with it.
+pub fn set_tracker_legacy(legacy: bool) {
+    if let Some(tracker) = TRACKER.get() {
+        if tracker.legacy.swap(legacy, Ordering::SeqCst) != legacy && tracker.is_live() {
+            tracker.update(check_all_statuses(&tracker.services, legacy));
+        }
+    }
+}
+
+/// Statuses from the event tracker when it's live, otherwise a fresh poll.
+pub fn current_statuses(services: &[Service], legacy: bool) -> Vec<ServiceStatus> {
+    let tracked = TRACKER
+        .get()
+        .and_then(|t| t.snapshot())
+        .and_then(|statuses| {
+            services
+                .iter()
+                .map(|s| statuses.iter().find(|st| st.name == s.name).cloned())
+                .collect::<Option<Vec<_>>>()
+        });
+
+    tracked.unwrap_or_else(|| check_all_statuses(services, legacy))
+}
+
+/// Changes whenever tracked statuses change; None while not live.
+pub fn tracker_version() -> Option<u64> {
+    TRACKER
+        .get()
+        .filter(|t| t.is_live())
+        .map(|t| t.version.load(Ordering::SeqCst))
+}
@@ -0,0 +1,191 @@
+use crate::config::{Config, Probe};
+use crate::docker::{get_service_status, run_bounded};
+use crate::service::Service;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Readiness {
+    Ready,
+    Unhealthy,
+    Timeout,
+}
+
+impl Readiness {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Readiness::Ready => "READY",
+            Readiness::Unhealthy => "UNHEALTHY",
+            Readiness::Timeout => "TIMEOUT",
+        }
+    }
+}
+
+pub struct ReadinessReport {
+    pub name: String,
+    pub readiness: Readiness,
+    /// Why it isn't ready; empty when it is
+    pub detail: String,
+}
+
+enum Check {
+    Ready,
+    Waiting(String),
+    Failed(String),
+}
+
+fn connect(addr: &str) -> Result<TcpStream, String> {
+    let addrs = addr
+        .to_socket_addrs()
+        .map_err(|e| format!("{}: {}", addr, e))?;
+    let mut last_error = format!("{}: no address", addr);
+    for socket in addrs {
+        match TcpStream::connect_timeout(&socket, PROBE_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = format!("{}: {}", addr, e),
+        }
+    }
+    Err(last_error)
+}
+
+/// Minimal HTTP/1.0 GET; only the status line matters.
+fn probe_http(url: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("{}: only http:// URLs are supported", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    // The port comes after the closing bracket of an IPv6 address, if any
+    let host_end = authority.rfind(']').unwrap_or(0);
+    let addr = if authority[host_end..].contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let mut stream = connect(&addr)?;
+    let _ = stream.set_read_timeout(Some(PROBE_TIMEOUT));
+    write!(
+        stream,
+        "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, authority
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut status_line)
+        .map_err(|e| e.to_string())?;
+    let code: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|c| c.parse().ok())
+        .ok_or_else(|| format!("{}: not an HTTP response", url))?;
+    if (200..400).contains(&code) {
+        Ok(())
+    } else {
+        Err(format!("{} answered HTTP {}", url, code))
+    }
+}
+
+fn check_probe(probe: &Probe) -> Check {
+    let result = match (&probe.http, &probe.tcp) {
+        (Some(url), _) => probe_http(url),
+        (None, Some(addr)) => connect(addr).map(|_| ()),
+        (None, None) => return Check::Failed("probe has neither tcp nor http".to_string()),
+    };
+    match result {
+        Ok(()) => Check::Ready,
+        Err(e) => Check::Waiting(e),
+    }
+}
+
+/// Docker's view: crashed or unhealthy containers fail right away, anything
+/// still starting keeps us waiting.
+fn check_containers(service: &Service, legacy: bool) -> Check {
+    let status = get_service_status(service, legacy);
+    if status.containers.is_empty() {
+        return Check::Waiting("no containers yet".to_string());
+    }
+
+    for c in &status.containers {
+        if c.health == "unhealthy" {
+            return Check::Failed(format!("{} is unhealthy", c.name));
+        }
+        if !c.is_running() && !c.is_ok() && c.state != "created" && c.state != "restarting" {
+            return Check::Failed(format!("{} exited with code {}", c.name, c.exit_code));
+        }
+    }
+    for c in &status.containers {
+        if c.state == "restarting" {
+            return Check::Waiting(format!("{} keeps restarting", c.name));
+        }
+        if c.state == "created" {
+            return Check::Waiting(format!("{} hasn't started", c.name));
+        }
+        if c.health == "starting" {
+            return Check::Waiting(format!("{} health check is starting", c.name));
+        }
+    }
+    Check::Ready
+}
+
+/// Starts waiting on every service in the background, using its configured
+/// probe or else its containers' state and healthchecks. Each poll checks the
+/// services still waiting at most `max_parallel` at a time; reports arrive as
+/// each service settles.
+pub fn spawn_readiness_checks(services: &[Service], config: &Config) -> Receiver<ReadinessReport> {
+    let (tx, rx) = mpsc::channel();
+    let deadline = Instant::now() + Duration::from_secs(config.health_timeout);
+    let legacy = config.legacy_compose;
+    let mut pending: Vec<(Service, Option<Probe>)> = services
+        .iter()
+        .map(|s| (s.clone(), config.probes.get(&s.name).cloned()))
+        .collect();
+
+    thread::spawn(move || {
+        while !pending.is_empty() {
+            let checks = run_bounded(pending.clone(), move |(service, probe)| match probe {
+                Some(probe) => check_probe(&probe),
+                None => check_containers(&service, legacy),
+            });
+
+            let mut waiting = Vec::new();
+            for ((service, probe), check) in pending.into_iter().zip(checks) {
+                let check = check.unwrap_or_else(|| Check::Failed("check panicked".to_string()));
+                let (readiness, detail) = match check {
+                    Check::Ready => (Readiness::Ready, String::new()),
+                    Check::Failed(detail) => (Readiness::Unhealthy, detail),
+                    Check::Waiting(detail) if Instant::now() >= deadline => {
+                        (Readiness::Timeout, detail)
+                    }
+                    Check::Waiting(_) => {
+                        waiting.push((service, probe));
+                        continue;
+                    }
+                };
+                let report = ReadinessReport {
+                    name: service.name,
+                    readiness,
+                    detail,
+                };
+                if tx.send(report).is_err() {
+                    return;
+                }
+            }
+            pending = waiting;
+            if !pending.is_empty() {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    });
+    rx
+}
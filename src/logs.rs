@@ -0,0 +1,568 @@
+use crate::docker::{
+    build_compose_cmd, kill_process_group, operation_timeout, output_with_timeout,
+    own_process_group, query_timeout, run_bounded, spawn_bounded, timed_out_message, OpResult,
+};
+use crate::service::Service;
+use chrono::Local;
+use colored::*;
+use crossterm::cursor::MoveToColumn;
+use crossterm::event::{poll, read, Event, KeyCode, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use regex::Regex;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, ErrorKind, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PREFIX_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+    Color::BrightRed,
+];
+
+struct LogLine {
+    source: usize,
+    text: String,
+    stderr: bool,
+}
+
+fn relay<R: Read + Send + 'static>(
+    pipe: R,
+    source: usize,
+    stderr: bool,
+    tx: Sender<LogLine>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for text in BufReader::new(pipe).lines().map_while(Result::ok) {
+            let line = LogLine {
+                source,
+                text,
+                stderr,
+            };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Colored, padded `name |` prefixes, one per service. A single service gets no
+/// prefix so its output looks exactly like `docker compose logs`.
+fn build_prefixes(services: &[Service]) -> Vec<String> {
+    if services.len() < 2 {
+        return vec![String::new(); services.len()];
+    }
+
+    let width = services.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    services
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let color = PREFIX_COLORS[i % PREFIX_COLORS.len()];
+            format!(
+                "{} ",
+                format!("{:<width$} |", s.name, width = width).color(color)
+            )
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LogOptions {
+    pub tail: Option<usize>,
+    pub since: Option<String>,
+    pub timestamps: bool,
+}
+
+impl LogOptions {
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(tail) = self.tail {
+            args.push("--tail".to_string());
+            args.push(tail.to_string());
+        }
+        if let Some(since) = &self.since {
+            args.push("--since".to_string());
+            args.push(since.clone());
+        }
+        if self.timestamps {
+            args.push("--timestamps".to_string());
+        }
+        args
+    }
+}
+
+/// Follows the logs of every given service at once, interleaving their lines.
+/// Ctrl+C or Esc stops all of them together.
+pub fn stream_logs(services: &[Service], legacy: bool, options: &LogOptions) {
+    let (tx, rx) = mpsc::channel();
+    let mut children: Vec<Child> = Vec::new();
+    let mut relays = Vec::new();
+    let interactive = std::io::stdin().is_terminal();
+
+    for (source, service) in services.iter().enumerate() {
+        let mut cmd = build_compose_cmd(service, legacy);
+        // Esc and Ctrl+C are read as keys then, so stopping is up to us, and
+        // has to reach the compose plugin too. Otherwise the terminal's
+        // Ctrl+C reaches the whole foreground group.
+        if interactive {
+            own_process_group(&mut cmd);
+        }
+        let mut child = match cmd
+            .args(["logs", "-f"])
+            .args(options.args())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+
+        if let Some(out) = child.stdout.take() {
+            relays.push(relay(out, source, false, tx.clone()));
+        }
+        if let Some(err) = child.stderr.take() {
+            relays.push(relay(err, source, true, tx.clone()));
+        }
+        children.push(child);
+    }
+    // Only the relays hold senders now, so the channel closes once every child is done
+    drop(tx);
+
+    let mut view = LogView::new(build_prefixes(services), interactive);
+
+    if interactive {
+        view.status("/ filter  SPACE pause  c clear filter  ESC quit");
+        let _ = enable_raw_mode();
+    }
+
+    loop {
+        match rx.recv_timeout(Duration::from_millis(50)) {
+            Ok(line) => view.emit(line),
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        if interactive && view.handle_keys() {
+            for child in children.iter_mut() {
+                kill_process_group(child);
+            }
+            break;
+        }
+    }
+
+    if interactive {
+        let _ = disable_raw_mode();
+    }
+    for child in children.iter_mut() {
+        let _ = child.wait();
+    }
+    for handle in relays {
+        let _ = handle.join();
+    }
+}
+
+/// Runs the same compose command on every service concurrently, relaying their
+/// output with service prefixes like the log view. Each result keeps a copy of
+/// what its service printed.
+pub fn run_streamed(services: &[Service], args: &[String], legacy: bool) -> Vec<OpResult> {
+    let (tx, rx) = mpsc::channel();
+    let args = args.to_vec();
+    let jobs: Vec<(usize, Service)> = services.iter().cloned().enumerate().collect();
+    // At most max_parallel commands run at once; each worker relays its
+    // command's lines here and reports how it ended
+    let finished = spawn_bounded(jobs, move |(source, service)| {
+        run_relayed(&service, &args, legacy, source, &tx)
+    });
+
+    let prefixes = build_prefixes(services);
+    let mut captured = vec![(String::new(), String::new()); services.len()];
+    // The channel closes once every job is done and the pool is gone
+    for line in rx {
+        // Build tools report progress on stderr; keep it all in one stream
+        println!("{}{}", prefixes[line.source], line.text);
+        let (stdout, stderr) = &mut captured[line.source];
+        let target = if line.stderr { stderr } else { stdout };
+        target.push_str(&line.text);
+        target.push('\n');
+    }
+
+    let mut endings: Vec<Option<Result<bool, String>>> = vec![None; services.len()];
+    for (index, ending) in finished {
+        endings[index] = Some(ending);
+    }
+    services
+        .iter()
+        .zip(endings.into_iter().zip(captured))
+        .map(|(service, (ending, (stdout, mut stderr)))| {
+            let ok = match ending {
+                Some(Ok(ok)) => ok,
+                Some(Err(e)) => {
+                    stderr.push_str(&e);
+                    false
+                }
+                None => {
+                    stderr.push_str("could not run docker compose");
+                    false
+                }
+            };
+            OpResult {
+                name: service.name.clone(),
+                ok,
+                stdout,
+                stderr,
+            }
+        })
+        .collect()
+}
+
+/// Runs one compose command for [`run_streamed`], sending its output lines
+/// to `tx` and killing it past the operation timeout. Whether it succeeded,
+/// or why it didn't finish.
+fn run_relayed(
+    service: &Service,
+    args: &[String],
+    legacy: bool,
+    source: usize,
+    tx: &Sender<LogLine>,
+) -> Result<bool, String> {
+    let mut child = own_process_group(build_compose_cmd(service, legacy).args(args))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|_| "could not start docker compose".to_string())?;
+
+    let mut relays = Vec::new();
+    if let Some(out) = child.stdout.take() {
+        relays.push(relay(out, source, false, tx.clone()));
+    }
+    if let Some(err) = child.stderr.take() {
+        relays.push(relay(err, source, true, tx.clone()));
+    }
+
+    let timeout = operation_timeout();
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status.success()),
+            Ok(None) if timeout > 0 && Instant::now() >= deadline => {
+                kill_process_group(&mut child);
+                let _ = child.wait();
+                break Err(timed_out_message(timeout));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(e) => break Err(e.to_string()),
+        }
+    };
+    for handle in relays {
+        let _ = handle.join();
+    }
+    status
+}
+
+/// Writes a non-following snapshot of the services' logs to a timestamped
+/// file in the current directory and returns its path.
+pub fn save_logs(
+    services: &[Service],
+    legacy: bool,
+    options: &LogOptions,
+) -> Result<PathBuf, String> {
+    let args = options.args();
+    let outputs = run_bounded(services.to_vec(), move |service| {
+        output_with_timeout(
+            build_compose_cmd(&service, legacy)
+                .args(["logs", "--no-color"])
+                .args(&args),
+            query_timeout(),
+        )
+    });
+
+    let mut contents = String::new();
+    for (service, output) in services.iter().zip(outputs) {
+        let output = output
+            .ok_or_else(|| format!("{}: log collection panicked", service.name))?
+            .map_err(|e| format!("{}: {}", service.name, e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!(
+                "{}: {}",
+                service.name,
+                stderr
+                    .lines()
+                    .rev()
+                    .find(|l| !l.trim().is_empty())
+                    .unwrap_or("docker compose logs failed")
+            ));
+        }
+
+        contents.push_str(&format!("==> {} <==\n", service.name));
+        contents.push_str(&String::from_utf8_lossy(&output.stdout));
+        contents.push_str(&String::from_utf8_lossy(&output.stderr));
+        contents.push('\n');
+    }
+
+    // Saves within the same second get a numbered suffix instead of
+    // overwriting each other
+    let dir = std::env::current_dir().map_err(|e| e.to_string())?;
+    let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut n = 1;
+    loop {
+        let name = match n {
+            1 => format!("dockerstrator-logs-{}.log", stamp),
+            n => format!("dockerstrator-logs-{}-{}.log", stamp, n),
+        };
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes())
+                    .map_err(|e| e.to_string())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// Lines kept while output is paused; older ones are dropped past this.
+const PAUSE_BUFFER_LIMIT: usize = 10_000;
+
+/// Filtering, highlighting and pausing for followed log lines.
+struct LogView {
+    prefixes: Vec<String>,
+    interactive: bool,
+    eol: &'static str,
+    levels: Vec<(Regex, Color)>,
+    filter: Option<Regex>,
+    filter_text: String,
+    /// Filter being typed after `/`; output is held back meanwhile
+    input: Option<String>,
+    paused: bool,
+    buffer: VecDeque<LogLine>,
+}
+
+impl LogView {
+    fn new(prefixes: Vec<String>, interactive: bool) -> Self {
+        let levels = [
+            (r"(?i)\b(error|err|fatal|panic|critical|crit)\b", Color::Red),
+            (r"(?i)\b(warn|warning)\b", Color::Yellow),
+            (r"(?i)\b(info|notice)\b", Color::Green),
+            (r"(?i)\b(debug|trace)\b", Color::BrightBlack),
+        ]
+        .iter()
+        .filter_map(|(pattern, color)| Regex::new(pattern).ok().map(|re| (re, *color)))
+        .collect();
+
+        LogView {
+            prefixes,
+            interactive,
+            // Use \r\n so lines display correctly when raw mode is active
+            eol: if interactive { "\r\n" } else { "\n" },
+            levels,
+            filter: None,
+            filter_text: String::new(),
+            input: None,
+            paused: false,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    fn emit(&mut self, line: LogLine) {
+        if self.paused || self.input.is_some() {
+            if self.buffer.len() == PAUSE_BUFFER_LIMIT {
+                self.buffer.pop_front();
+            }
+            self.buffer.push_back(line);
+            return;
+        }
+
+        if let Some(filter) = &self.filter {
+            if !filter.is_match(&line.text) {
+                return;
+            }
+        }
+
+        let text = if self.interactive {
+            self.highlight(&line.text)
+        } else {
+            line.text
+        };
+        let prefix = &self.prefixes[line.source];
+        if line.stderr {
+            let _ = write!(std::io::stderr().lock(), "{}{}{}", prefix, text, self.eol);
+        } else {
+            let _ = write!(std::io::stdout().lock(), "{}{}{}", prefix, text, self.eol);
+        }
+    }
+
+    fn flush_buffer(&mut self) {
+        // Lines go back into the buffer if we are still paused
+        for line in std::mem::take(&mut self.buffer) {
+            self.emit(line);
+        }
+    }
+
+    fn status(&self, message: &str) {
+        let _ = write!(
+            std::io::stdout().lock(),
+            "{}{}",
+            format!("-- {} --", message).bright_black(),
+            self.eol
+        );
+    }
+
+    /// Filter matches are underlined, level keywords colored; filter wins on overlap.
+    fn highlight(&self, text: &str) -> String {
+        let mut spans: Vec<(usize, usize, Option<Color>)> = Vec::new();
+        if let Some(filter) = &self.filter {
+            spans.extend(
+                filter
+                    .find_iter(text)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| (m.start(), m.end(), None)),
+            );
+        }
+        for (re, color) in &self.levels {
+            for m in re.find_iter(text) {
+                if !spans.iter().any(|(s, e, _)| m.start() < *e && *s < m.end()) {
+                    spans.push((m.start(), m.end(), Some(*color)));
+                }
+            }
+        }
+        if spans.is_empty() {
+            return text.to_string();
+        }
+        spans.sort_by_key(|(start, _, _)| *start);
+
+        let mut out = String::with_capacity(text.len() + spans.len() * 10);
+        let mut pos = 0;
+        for (start, end, color) in spans {
+            out.push_str(&text[pos..start]);
+            let part = &text[start..end];
+            let styled = match color {
+                Some(color) => part.color(color).bold(),
+                None => part.bold().underline(),
+            };
+            out.push_str(&styled.to_string());
+            pos = end;
+        }
+        out.push_str(&text[pos..]);
+        out
+    }
+
+    /// Drains pending key events. Returns true when the user asked to stop.
+    fn handle_keys(&mut self) -> bool {
+        while let Ok(true) = poll(Duration::from_millis(0)) {
+            let key = match read() {
+                Ok(Event::Key(key)) => key,
+                _ => continue,
+            };
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                return true;
+            }
+
+            if let Some(input) = self.input.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) => input.push(c),
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Enter => {
+                        let input = self.input.take().unwrap_or_default();
+                        self.set_filter(input);
+                        self.flush_buffer();
+                        continue;
+                    }
+                    KeyCode::Esc => {
+                        self.input = None;
+                        self.clear_input_line();
+                        self.status("filter unchanged");
+                        self.flush_buffer();
+                        continue;
+                    }
+                    _ => {}
+                }
+                self.draw_input();
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Esc => return true,
+                KeyCode::Char('/') => {
+                    self.input = Some(self.filter_text.clone());
+                    self.draw_input();
+                }
+                KeyCode::Char(' ') | KeyCode::Char('p') => {
+                    self.paused = !self.paused;
+                    if self.paused {
+                        self.status("paused, SPACE to resume");
+                    } else {
+                        self.status(&format!("resumed ({} buffered lines)", self.buffer.len()));
+                        self.flush_buffer();
+                    }
+                }
+                KeyCode::Char('c') if self.filter.is_some() => {
+                    self.set_filter(String::new());
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Plain text unless it parses as a regex; case-insensitive unless it has uppercase.
+    fn set_filter(&mut self, input: String) {
+        self.clear_input_line();
+        let input = input.trim().to_string();
+        if input.is_empty() {
+            self.filter = None;
+            self.filter_text.clear();
+            self.status("filter cleared");
+            return;
+        }
+
+        let pattern = match Regex::new(&input) {
+            Ok(_) => input.clone(),
+            Err(_) => regex::escape(&input),
+        };
+        let pattern = if input.chars().any(|c| c.is_uppercase()) {
+            pattern
+        } else {
+            format!("(?i){}", pattern)
+        };
+        match Regex::new(&pattern) {
+            Ok(re) => {
+                self.filter = Some(re);
+                self.status(&format!("filter: {}", input));
+                self.filter_text = input;
+            }
+            Err(e) => self.status(&format!("invalid filter: {}", e)),
+        }
+    }
+
+    fn draw_input(&self) {
+        let input = self.input.as_deref().unwrap_or_default();
+        let mut out = std::io::stdout().lock();
+        let _ = execute!(out, MoveToColumn(0), Clear(ClearType::CurrentLine));
+        let _ = write!(out, "{}{}", "/".cyan().bold(), input);
+        let _ = out.flush();
+    }
+
+    fn clear_input_line(&self) {
+        let _ = execute!(
+            std::io::stdout(),
+            MoveToColumn(0),
+            Clear(ClearType::CurrentLine)
+        );
+    }
+}
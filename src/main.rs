@@ -1,43 +1,100 @@
+mod cli;
 mod config;
+mod dashboard;
+mod deps;
 mod docker;
+mod events;
+mod health;
+mod logs;
 mod ops;
+mod ports;
 mod service;
+mod snapshot;
+mod state;
+mod stats;
 mod ui;
 
+use cli::Command;
 use colored::*;
 use config::load_config;
 use crossterm::execute;
 use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
 use service::find_services;
 use std::io::stdout;
-use std::path::PathBuf;
 use ui::clear_screen;
 
 fn main() {
-    let target_dir = std::env::args()
-        .nth(1)
-        .map(PathBuf::from)
-        .unwrap_or_else(|| PathBuf::from("."));
+    let cli = match cli::parse_args(std::env::args().skip(1).collect()) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}", format!("Error: {}", e).red());
+            eprintln!("\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(Command::Help) = cli.command {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    let target_dir = cli.dir;
 
     if let Err(e) = std::env::set_current_dir(&target_dir) {
         println!(
             "{}",
             format!("Error: Cannot access directory {:?}: {}", target_dir, e).red()
         );
-        return;
+        std::process::exit(1);
+    }
+
+    if let Some(Command::CheckConfig) = cli.command {
+        std::process::exit(cli::check_config());
     }
 
     let mut config = load_config();
-    let services = find_services(&config);
+    if !config.layers.reports().is_empty() {
+        for report in config.layers.reports() {
+            cli::print_config_report(report);
+        }
+        if config.layers.reports().iter().any(|r| !r.errors.is_empty()) {
+            eprintln!(
+                "{}",
+                "Config files with errors are ignored. Run `dockerstrator config check` to recheck."
+                    .bright_black()
+            );
+        }
+        if cli.command.is_none() {
+            ui::pause();
+        }
+    }
+    if let Err(e) = state::read_state() {
+        eprintln!("{}", format!("Error: {}", e).red());
+        eprintln!(
+            "{}",
+            "Remembered selections and snapshots won't be saved until the state file is fixed."
+                .bright_black()
+        );
+        if cli.command.is_none() {
+            ui::pause();
+        }
+    }
+    docker::apply_limits(&config);
+    let services = find_services(&config, cli.command.is_none());
 
     if services.is_empty() {
         println!(
             "{}",
             "No docker-compose.yml found in this directory structure.".red()
         );
-        return;
+        std::process::exit(1);
+    }
+
+    if let Some(command) = cli.command {
+        std::process::exit(cli::run(command, &services, &config));
     }
 
+    events::start_tracker(&services, config.legacy_compose);
     let _ = execute!(stdout(), EnterAlternateScreen);
 
     loop {
@@ -53,4 +110,5 @@ fn main() {
     }
 
     let _ = execute!(stdout(), LeaveAlternateScreen);
+    events::stop_tracker();
 }
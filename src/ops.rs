@@ -1,13 +1,28 @@
 use crate::config::{save_config, Config};
-use crate::docker::{build_compose_cmd, check_all_statuses, run_parallel};
+use crate::dashboard::show_dashboard;
+use crate::deps::{DependencyGraph, Direction};
+use crate::docker::{
+    apply_limits, build_compose_cmd, get_compose_services, pull_images, run_parallel, BuildOptions,
+    OpResult, PullResult, ServiceStatus, State,
+};
+use crate::events::{current_statuses, refresh_statuses, set_tracker_legacy};
+use crate::health::{spawn_readiness_checks, Readiness, ReadinessReport};
+use crate::logs::{run_streamed, save_logs, stream_logs, LogOptions};
+use crate::ports::{conflicts_for_start, describe_conflict, find_conflicts, published_ports};
 use crate::service::Service;
-use crate::ui::{clear_screen, interactive_menu, pause};
+use crate::snapshot::{
+    delete_snapshot, list_snapshots, restore_plan, save_snapshot, validate_name, Snapshot,
+};
+use crate::state::{load_project, update_project};
+use crate::stats::show_usage;
+use crate::ui::{
+    clear_screen, interactive_menu, pause, state_label, with_terminal_released, ProgressTable,
+};
 use colored::*;
-use crossterm::event::{poll, read, Event, KeyCode, KeyModifiers};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use inquire::{MultiSelect, Select};
-use std::io::{BufRead, BufReader, Write};
-use std::thread;
+use std::io::Write;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
 
 pub fn print_header(service_count: usize) {
     println!("\n{}\n", "Docker Services Orchestrator".bold().cyan());
@@ -15,44 +30,90 @@ pub fn print_header(service_count: usize) {
 }
 
 pub fn show_main_menu() -> Option<String> {
-    match interactive_menu(
-        "Select an action:",
-        &[
-            ("s", "Start services"),
-            ("p", "Stop services"),
-            ("r", "Restart services"),
-            ("t", "Show status"),
-            ("l", "Stream logs"),
-            ("c", "Cleanup volumes"),
-            ("g", "Settings"),
-            ("q", "Exit"),
-        ],
-    ) {
+    let state = load_project();
+    let repeat = state.last_action.as_ref().map(|action| {
+        let names = state.selections.get(action).cloned().unwrap_or_default();
+        format!("Repeat last action ({}: {})", action, names.join(", "))
+    });
+
+    let mut items = vec![
+        ("s", "Start services"),
+        ("p", "Stop services"),
+        ("r", "Restart services"),
+        ("u", "Pull images"),
+        ("b", "Build images"),
+        ("t", "Show status"),
+        ("d", "Live dashboard"),
+        ("m", "Resource usage"),
+        ("o", "Port overview"),
+        ("l", "Stream logs"),
+        ("e", "Open shell"),
+        ("c", "Cleanup volumes"),
+        ("w", "Workspace snapshots"),
+        ("g", "Settings"),
+    ];
+    if let Some(label) = &repeat {
+        items.push(("a", label.as_str()));
+    }
+    items.push(("q", "Exit"));
+
+    match interactive_menu("Select an action:", &items) {
         Some('s') => Some("Start".to_string()),
         Some('p') => Some("Stop".to_string()),
         Some('r') => Some("Restart".to_string()),
+        Some('u') => Some("Pull".to_string()),
+        Some('b') => Some("Build".to_string()),
         Some('t') => Some("Status".to_string()),
+        Some('d') => Some("Dashboard".to_string()),
+        Some('m') => Some("Usage".to_string()),
+        Some('o') => Some("Ports".to_string()),
         Some('l') => Some("Logs".to_string()),
+        Some('e') => Some("Shell".to_string()),
         Some('c') => Some("Cleanup".to_string()),
+        Some('w') => Some("Snapshots".to_string()),
         Some('g') => Some("Settings".to_string()),
+        Some('a') => Some("Repeat".to_string()),
         _ => None,
     }
 }
 
 pub fn handle_action(action: &str, services: &[Service], config: &mut Config) {
+    if action == "Repeat" {
+        if let Some(last) = load_project().last_action {
+            run_action(&last, services, config, true);
+        }
+    } else {
+        run_action(action, services, config, false);
+    }
+}
+
+/// With `repeat` set, actions that pick services reuse their last selection
+/// instead of asking.
+fn run_action(action: &str, services: &[Service], config: &mut Config, repeat: bool) {
+    let pick = Pick {
+        action: action.to_string(),
+        repeat,
+    };
     match action {
-        "Start" => start_services(services, config),
-        "Stop" => stop_services(services, config),
-        "Restart" => restart_services(services, config),
+        "Start" => start_services(services, config, &pick),
+        "Stop" => stop_services(services, config, &pick),
+        "Restart" => restart_services(services, config, &pick),
+        "Pull" => pull_services(services, config, &pick),
+        "Build" => build_services(services, config, &pick),
         "Status" => show_status(services, config),
-        "Logs" => show_logs(services, config),
-        "Cleanup" => cleanup_data(services, config),
-        "Settings" => show_settings(config),
+        "Dashboard" => show_dashboard(services, config),
+        "Usage" => show_usage(services, config),
+        "Ports" => show_ports(services, config),
+        "Logs" => show_logs(services, config, &pick),
+        "Shell" => open_shell(services, config),
+        "Cleanup" => cleanup_data(services, config, &pick),
+        "Snapshots" => manage_snapshots(services, config),
+        "Settings" => show_settings(services, config),
         _ => {}
     }
 }
 
-fn print_result(name: &str, ok: bool) {
+pub fn print_result(name: &str, ok: bool) {
     if ok {
         println!("  {} ... {}", name.cyan(), "OK".green());
     } else {
@@ -60,6 +121,163 @@ fn print_result(name: &str, ok: bool) {
     }
 }
 
+/// Runs a compose command on every service at once behind a live progress
+/// table. Docker's own output is captured per service, not shown.
+pub fn run_with_progress(services: Vec<Service>, args: &[&str], legacy: bool) -> Vec<OpResult> {
+    let mut table = ProgressTable::new(&services, "running");
+    let results = run_parallel(services.clone(), args, legacy, |progress| {
+        table.update(progress)
+    });
+    refresh_statuses(&services);
+    results
+}
+
+/// Runs `args` wave by wave in dependency order. When starting, stacks whose
+/// dependencies failed are skipped instead of started against a broken stack.
+pub fn run_ordered(
+    selected: &[Service],
+    graph: &DependencyGraph,
+    direction: Direction,
+    args: &[&str],
+    legacy: bool,
+) -> Result<Vec<OpResult>, String> {
+    let waves = graph.waves(selected, direction)?;
+    let mut results: Vec<OpResult> = Vec::new();
+
+    for (i, wave) in waves.iter().enumerate() {
+        if waves.len() > 1 {
+            let names: Vec<&str> = wave.iter().map(|s| s.name.as_str()).collect();
+            println!(
+                "{}",
+                format!("Wave {}/{}: {}", i + 1, waves.len(), names.join(", ")).bright_black()
+            );
+        }
+
+        let mut runnable = Vec::new();
+        for service in wave {
+            let failed_dep = graph
+                .requires(&service.name)
+                .into_iter()
+                .find(|dep| results.iter().any(|r| &r.name == dep && !r.ok));
+            match failed_dep {
+                Some(dep) if direction == Direction::Start => {
+                    println!("  {} ... {}", service.name.cyan(), "SKIPPED".yellow());
+                    results.push(OpResult::failed(
+                        &service.name,
+                        &format!("Skipped: depends on {}, which failed to start", dep),
+                    ));
+                }
+                _ => runnable.push(service.clone()),
+            }
+        }
+        if !runnable.is_empty() {
+            results.extend(run_with_progress(runnable, args, legacy));
+        }
+    }
+
+    Ok(results)
+}
+
+/// Waits for freshly started services to become ready behind a progress
+/// table, then says why any of them aren't. Returns true if all are ready.
+pub fn wait_until_ready(services: &[Service], config: &Config) -> bool {
+    println!(
+        "{}\n",
+        "Waiting for services to become ready...".yellow().bold()
+    );
+    let rx = spawn_readiness_checks(services, config);
+    let mut table = ProgressTable::new(services, "waiting");
+    let mut reports: Vec<ReadinessReport> = Vec::new();
+
+    while reports.len() < services.len() {
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(report) => {
+                let color = match report.readiness {
+                    Readiness::Ready => Color::Green,
+                    Readiness::Unhealthy => Color::Red,
+                    Readiness::Timeout => Color::Yellow,
+                };
+                table.finish(&report.name, report.readiness.label(), color);
+                reports.push(report);
+            }
+            Err(RecvTimeoutError::Timeout) => table.tick(),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let ready = reports
+        .iter()
+        .filter(|r| r.readiness == Readiness::Ready)
+        .count();
+    println!(
+        "\n{}",
+        format!("{}/{} services ready", ready, services.len()).green()
+    );
+    for report in reports.iter().filter(|r| r.readiness != Readiness::Ready) {
+        println!("  {}: {}", report.name.cyan(), report.detail);
+    }
+    println!();
+    ready == services.len()
+}
+
+pub fn summarize(results: &[OpResult], what: &str) {
+    let ok_count = results.iter().filter(|r| r.ok).count();
+    println!(
+        "\n{}\n",
+        format!("{}/{} {}", ok_count, results.len(), what).green()
+    );
+}
+
+/// Pauses before going back to the menu, or when something failed, lets the
+/// user read its captured output instead.
+fn review_failures(results: &[OpResult]) {
+    if results.iter().all(|r| r.ok) {
+        pause();
+    } else {
+        inspect_failures(results);
+    }
+}
+
+/// Offers the captured output of every failed service until the user moves on.
+fn inspect_failures(results: &[OpResult]) {
+    let failed: Vec<&OpResult> = results.iter().filter(|r| !r.ok).collect();
+    if failed.is_empty() {
+        return;
+    }
+
+    loop {
+        let mut options: Vec<String> = failed
+            .iter()
+            .map(|r| format!("Inspect {}", r.name))
+            .collect();
+        options.push("Continue".to_string());
+
+        let title = format!("{} service(s) failed:", failed.len());
+        match Select::new(&title, options).raw_prompt() {
+            Ok(choice) if choice.index < failed.len() => show_output(failed[choice.index]),
+            _ => return,
+        }
+    }
+}
+
+fn show_output(result: &OpResult) {
+    clear_screen();
+    println!("\n{}\n", format!("Output of {}", result.name).bold().cyan());
+
+    let stderr = result.stderr.trim_end();
+    let stdout = result.stdout.trim_end();
+    if stderr.is_empty() && stdout.is_empty() {
+        println!("{}", "(no output captured)".bright_black());
+    }
+    if !stderr.is_empty() {
+        println!("{}\n{}\n", "stderr:".red().bold(), stderr);
+    }
+    if !stdout.is_empty() {
+        println!("{}\n{}\n", "stdout:".bold(), stdout);
+    }
+    pause();
+}
+
 fn confirm(question: &str) -> bool {
     matches!(
         interactive_menu(question, &[("y", "Yes"), ("n", "No")]),
@@ -67,61 +285,326 @@ fn confirm(question: &str) -> bool {
     )
 }
 
-fn save_with_feedback(config: &Config) {
+fn save_with_feedback(config: &mut Config) {
     match save_config(config) {
-        Ok(_) => println!("{}", "Saved.".green()),
+        Ok(saved) => {
+            if saved.written {
+                println!("{}", format!("Saved to {}.", saved.path.display()).green());
+            }
+            for (key, file) in &saved.overridden {
+                println!(
+                    "{}",
+                    format!(
+                        "Not saved: {} is overridden by {}. Switch the save target with l under Settings to save it there.",
+                        key, file
+                    )
+                    .yellow()
+                );
+            }
+        }
         Err(e) => println!("{}", format!("Error saving config: {}", e).red()),
     }
 }
 
-fn select_services(services: &[Service]) -> Vec<Service> {
-    let service_names: Vec<&str> = services.iter().map(|s| s.name.as_str()).collect();
+/// The menu action picking services, and whether it's being repeated.
+struct Pick {
+    action: String,
+    repeat: bool,
+}
+
+/// Remembers what was picked for the next time this action runs.
+fn remember_selection(pick: &Pick, selected: &[Service]) {
+    if selected.is_empty() {
+        return;
+    }
+    // An unreadable state file is reported at startup
+    let _ = update_project(|state| {
+        state.last_action = Some(pick.action.clone());
+        state.selections.insert(
+            pick.action.clone(),
+            selected.iter().map(|s| s.name.clone()).collect(),
+        );
+    });
+}
+
+/// The services this action picked last time, among `services`.
+fn last_selection(services: &[Service], pick: &Pick) -> Vec<Service> {
+    let state = load_project();
+    let names = state
+        .selections
+        .get(&pick.action)
+        .cloned()
+        .unwrap_or_default();
+    services
+        .iter()
+        .filter(|s| names.contains(&s.name))
+        .cloned()
+        .collect()
+}
+
+/// When repeating, the last selection; None when the user should be asked.
+fn repeated_selection(services: &[Service], pick: &Pick) -> Option<Vec<Service>> {
+    if !pick.repeat {
+        return None;
+    }
+    let selected = last_selection(services, pick);
+    if selected.is_empty() {
+        println!(
+            "\n{}",
+            "None of the last selected services are available for this action.".yellow()
+        );
+        pause();
+    }
+    Some(selected)
+}
+
+fn select_services(services: &[Service], pick: &Pick) -> Vec<Service> {
+    select_services_labeled(services, pick, |s| s.name.clone())
+}
+
+/// Multi-select with last time's choice for this action pre-checked.
+fn select_services_labeled(
+    services: &[Service],
+    pick: &Pick,
+    label: impl Fn(&Service) -> String,
+) -> Vec<Service> {
+    if let Some(selected) = repeated_selection(services, pick) {
+        return selected;
+    }
+
+    let labels: Vec<String> = services.iter().map(label).collect();
+    let last = last_selection(services, pick);
+    let defaults: Vec<usize> = services
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| last.iter().any(|l| l.name == s.name))
+        .map(|(i, _)| i)
+        .collect();
 
-    match MultiSelect::new("Select services:", service_names)
-        .with_help_message("↑↓ navigate  SPACE select  ENTER confirm  ESC cancel")
+    let selected: Vec<Service> = match MultiSelect::new("Select services:", labels)
+        .with_help_message("↑↓ navigate  SPACE select  → all  ← none  ENTER confirm  ESC cancel")
+        .with_default(&defaults)
         .with_formatter(&|items| {
-            let selected: Vec<&str> = items.iter().map(|s| *s.value).collect();
-            if selected.is_empty() {
+            if items.is_empty() {
                 "None selected".to_string()
             } else {
-                format!("{} selected", selected.len())
+                format!("{} selected", items.len())
             }
         })
-        .prompt()
+        .raw_prompt()
     {
-        Ok(selected) => services
+        Ok(selected) => selected
             .iter()
-            .filter(|s| selected.contains(&s.name.as_str()))
-            .cloned()
+            .map(|item| services[item.index].clone())
             .collect(),
         Err(_) => Vec::new(),
+    };
+    remember_selection(pick, &selected);
+    selected
+}
+
+/// Offers the configured groups as one-keystroke shortcuts before falling back
+/// to picking services one by one. Only members found in `services` count, and
+/// groups with none of them are left out.
+fn choose_services(
+    services: &[Service],
+    config: &Config,
+    pick: &Pick,
+    label: impl Fn(&Service) -> String,
+) -> Vec<Service> {
+    if let Some(selected) = repeated_selection(services, pick) {
+        return selected;
+    }
+
+    let groups: Vec<(&String, Vec<Service>)> = config
+        .groups
+        .iter()
+        .map(|(name, members)| {
+            let found = services
+                .iter()
+                .filter(|s| members.contains(&s.name))
+                .cloned()
+                .collect::<Vec<_>>();
+            (name, found)
+        })
+        .filter(|(_, found)| !found.is_empty())
+        .take(9)
+        .collect();
+
+    if groups.is_empty() {
+        return select_services_labeled(services, pick, label);
     }
+
+    let keys: Vec<String> = (1..=groups.len()).map(|i| i.to_string()).collect();
+    let labels: Vec<String> = groups
+        .iter()
+        .map(|(name, found)| {
+            let names: Vec<&str> = found.iter().map(|s| s.name.as_str()).collect();
+            format!("@{} ({})", name, names.join(", "))
+        })
+        .collect();
+    let mut items: Vec<(&str, &str)> = keys
+        .iter()
+        .zip(&labels)
+        .map(|(key, label)| (key.as_str(), label.as_str()))
+        .collect();
+    items.push(("s", "Select services..."));
+    items.push(("q", "Back"));
+
+    match interactive_menu("Choose services", &items) {
+        Some('s') => select_services_labeled(services, pick, label),
+        Some(key) => {
+            let selected = key
+                .to_digit(10)
+                .and_then(|d| groups.get((d as usize).wrapping_sub(1)))
+                .map(|(_, found)| found.clone())
+                .unwrap_or_default();
+            remember_selection(pick, &selected);
+            selected
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Stacks outside `selected` that `selected` need, each with the selected
+/// stacks that need it. With `dependents` set, the direction flips: stacks
+/// outside `selected` that need one of `selected`.
+fn related_outside(
+    selected: &[Service],
+    services: &[Service],
+    graph: &DependencyGraph,
+    dependents: bool,
+) -> Vec<(Service, Vec<String>)> {
+    let is_selected = |name: &str| selected.iter().any(|s| s.name == name);
+    services
+        .iter()
+        .filter(|s| !is_selected(&s.name))
+        .filter_map(|other| {
+            let related: Vec<String> = selected
+                .iter()
+                .filter(|s| {
+                    if dependents {
+                        graph.requires(&other.name).contains(&s.name)
+                    } else {
+                        graph.requires(&s.name).contains(&other.name)
+                    }
+                })
+                .map(|s| s.name.clone())
+                .collect();
+            (!related.is_empty()).then(|| (other.clone(), related))
+        })
+        .collect()
 }
 
-fn start_services(services: &[Service], config: &Config) {
-    let selected = select_services(services);
+fn start_services(services: &[Service], config: &Config, pick: &Pick) {
+    let mut selected = choose_services(services, config, pick, |s| s.name.clone());
 
     if selected.is_empty() {
         return;
     }
 
+    let statuses = current_statuses(services, config.legacy_compose);
+    let graph = DependencyGraph::build(services, config);
+    // Crashed or exited dependencies are just as unavailable as stopped ones
+    let state_of = |name: &str| {
+        statuses
+            .iter()
+            .find(|st| st.name == name)
+            .filter(|st| !st.running)
+            .map(|st| st.state)
+    };
+    let missing: Vec<(Service, Vec<String>)> = related_outside(&selected, services, &graph, false)
+        .into_iter()
+        .filter(|(dep, _)| state_of(&dep.name).is_some())
+        .collect();
+    if !missing.is_empty() {
+        let list = missing
+            .iter()
+            .map(|(dep, needed_by)| {
+                let state = state_of(&dep.name).map(|st| st.label()).unwrap_or_default();
+                format!(
+                    "  - {} [{}] (needed by {})",
+                    dep.name,
+                    state,
+                    needed_by.join(", ")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let question = format!(
+            "Required stacks that aren't running:\n{}\n\nStart them too?",
+            list
+        );
+        if confirm(&question) {
+            selected = services
+                .iter()
+                .filter(|s| {
+                    selected.iter().any(|x| x.name == s.name)
+                        || missing.iter().any(|(dep, _)| dep.name == s.name)
+                })
+                .cloned()
+                .collect();
+        }
+    }
+
+    let running: Vec<String> = statuses
+        .into_iter()
+        .filter(|s| s.running)
+        .map(|s| s.name)
+        .collect();
+    let conflicts = conflicts_for_start(&selected, services, &running);
+    if !conflicts.is_empty() {
+        println!("\n{}", "Port conflicts detected:".yellow().bold());
+        for conflict in &conflicts {
+            println!("  {}", describe_conflict(conflict).yellow());
+        }
+        println!();
+        if !confirm("Start anyway?") {
+            return;
+        }
+    }
+
     println!("\n{}\n", "Starting services...".yellow().bold());
-    let results = run_parallel(selected, &["up", "-d"], config.legacy_compose);
-    for (name, ok) in &results {
-        print_result(name, *ok);
+    match run_ordered(
+        &selected,
+        &graph,
+        Direction::Start,
+        &["up", "-d"],
+        config.legacy_compose,
+    ) {
+        Ok(results) => {
+            summarize(&results, "services started");
+            if config.wait_healthy {
+                let started: Vec<Service> = selected
+                    .iter()
+                    .filter(|s| results.iter().any(|r| r.name == s.name && r.ok))
+                    .cloned()
+                    .collect();
+                if !started.is_empty() {
+                    wait_until_ready(&started, config);
+                }
+            }
+            review_failures(&results);
+        }
+        Err(e) => {
+            println!("{}\n", e.red());
+            pause();
+        }
     }
-    let ok_count = results.iter().filter(|(_, ok)| *ok).count();
-    println!("\n{}\n", format!("{}/{} services started", ok_count, results.len()).green());
-    pause();
 }
 
-fn stop_services(services: &[Service], config: &Config) {
+fn stop_services(services: &[Service], config: &Config, pick: &Pick) {
     print!("{}", "Checking service status...".bright_black());
     let _ = std::io::stdout().flush();
-    let statuses = check_all_statuses(services, config.legacy_compose);
+    let statuses = current_statuses(services, config.legacy_compose);
+    // Anything with containers left over (including crashed ones) can be brought down
     let running: Vec<Service> = services
         .iter()
-        .filter(|s| statuses.iter().any(|(name, up)| name == &s.name && *up))
+        .filter(|s| {
+            statuses
+                .iter()
+                .any(|st| st.name == s.name && st.state != State::Down)
+        })
         .cloned()
         .collect();
     println!();
@@ -132,7 +615,12 @@ fn stop_services(services: &[Service], config: &Config) {
         return;
     }
 
-    let selected = select_services(&running);
+    let selected = choose_services(&running, config, pick, |s| {
+        match statuses.iter().find(|st| st.name == s.name) {
+            Some(st) if st.state != State::Up => format!("{} [{}]", s.name, st.state.label()),
+            _ => s.name.clone(),
+        }
+    });
 
     if selected.is_empty() {
         return;
@@ -143,180 +631,816 @@ fn stop_services(services: &[Service], config: &Config) {
         .map(|s| format!("  - {}", s.name))
         .collect::<Vec<_>>()
         .join("\n");
+    let graph = DependencyGraph::build(services, config);
+    let dependents: Vec<String> = related_outside(&selected, services, &graph, true)
+        .into_iter()
+        .filter(|(dependent, _)| {
+            statuses
+                .iter()
+                .any(|st| st.name == dependent.name && st.running)
+        })
+        .map(|(dependent, needs)| format!("  - {} (needs {})", dependent.name, needs.join(", ")))
+        .collect();
+    let warning = if dependents.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "\n\n{}\n{}",
+            "Warning: still running and relying on them:",
+            dependents.join("\n")
+        )
+    };
     let question = format!(
-        "Services to stop:\n{}\n\nStop {} service(s)?",
+        "Services to stop:\n{}{}\n\nStop {} service(s)?",
         services_list,
+        warning,
         selected.len()
     );
 
     if confirm(&question) {
         println!("\n{}\n", "Stopping services...".yellow());
-        let results = run_parallel(selected, &["down"], config.legacy_compose);
-        for (name, ok) in &results {
-            print_result(name, *ok);
+        match run_ordered(
+            &selected,
+            &graph,
+            Direction::Stop,
+            &["down"],
+            config.legacy_compose,
+        ) {
+            Ok(results) => {
+                summarize(&results, "services stopped");
+                review_failures(&results);
+            }
+            Err(e) => {
+                println!("{}\n", e.red());
+                pause();
+            }
         }
-        let ok_count = results.iter().filter(|(_, ok)| *ok).count();
-        println!("\n{}\n", format!("{}/{} services stopped", ok_count, results.len()).green());
-        pause();
     }
 }
 
-fn restart_services(services: &[Service], config: &Config) {
-    let selected = select_services(services);
+fn restart_services(services: &[Service], config: &Config, pick: &Pick) {
+    let selected = choose_services(services, config, pick, |s| s.name.clone());
 
     if selected.is_empty() {
         return;
     }
 
     println!("\n{}\n", "Restarting services...".yellow().bold());
-    let results = run_parallel(selected, &["restart"], config.legacy_compose);
-    for (name, ok) in &results {
-        print_result(name, *ok);
+    let results = run_with_progress(selected, &["restart"], config.legacy_compose);
+    summarize(&results, "services restarted");
+    review_failures(&results);
+}
+
+pub fn print_pull_result(pull: &PullResult) {
+    let result = &pull.result;
+    if !result.ok {
+        print_result(&result.name, false);
+    } else if pull.updated.is_empty() {
+        println!(
+            "  {} ... {} {}",
+            result.name.cyan(),
+            "OK".green(),
+            "(up to date)".bright_black()
+        );
+    } else {
+        println!(
+            "  {} ... {} {}",
+            result.name.cyan(),
+            "OK".green(),
+            format!("({} updated)", pull.updated.join(", ")).yellow()
+        );
+    }
+}
+
+/// Running services among the results whose images changed; the ones worth recreating.
+pub fn services_to_recreate(
+    services: &[Service],
+    results: &[PullResult],
+    legacy: bool,
+) -> Vec<Service> {
+    let updated: Vec<Service> = services
+        .iter()
+        .filter(|s| {
+            results
+                .iter()
+                .any(|r| r.result.name == s.name && !r.updated.is_empty())
+        })
+        .cloned()
+        .collect();
+
+    current_statuses(&updated, legacy)
+        .into_iter()
+        .zip(updated)
+        .filter(|(status, _)| status.running)
+        .map(|(_, service)| service)
+        .collect()
+}
+
+fn pull_services(services: &[Service], config: &Config, pick: &Pick) {
+    let selected = select_services(services, pick);
+
+    if selected.is_empty() {
+        return;
+    }
+
+    println!("\n{}\n", "Pulling images...".yellow().bold());
+    let mut table = ProgressTable::new(&selected, "pulling");
+    let results = pull_images(selected.clone(), config.legacy_compose, |progress| {
+        table.update(progress)
+    });
+    // Replaced by the rows below, which also say what got updated
+    table.clear();
+    for result in &results {
+        print_pull_result(result);
+    }
+    let pulled: Vec<OpResult> = results.iter().map(|r| r.result.clone()).collect();
+    summarize(&pulled, "services pulled");
+
+    let to_recreate = services_to_recreate(&selected, &results, config.legacy_compose);
+    if to_recreate.is_empty() {
+        review_failures(&pulled);
+        return;
+    }
+    inspect_failures(&pulled);
+
+    let question = format!(
+        "Recreate {} running service(s) with updated images?",
+        to_recreate.len()
+    );
+    if confirm(&question) {
+        println!("\n{}\n", "Recreating services...".yellow().bold());
+        let results = run_with_progress(to_recreate, &["up", "-d"], config.legacy_compose);
+        summarize(&results, "services recreated");
+        review_failures(&results);
     }
-    let ok_count = results.iter().filter(|(_, ok)| *ok).count();
-    println!("\n{}\n", format!("{}/{} services restarted", ok_count, results.len()).green());
+}
+
+/// Streams `compose build` for every service, then optionally recreates the
+/// ones that built successfully. Returns the results of both steps.
+pub fn build_and_start(
+    services: &[Service],
+    options: &BuildOptions,
+    start: bool,
+    legacy: bool,
+) -> Vec<OpResult> {
+    let mut results = run_streamed(services, &options.args(), legacy);
+    println!();
+    for result in &results {
+        print_result(&result.name, result.ok);
+    }
+    summarize(&results, "services built");
+
+    if start {
+        let built: Vec<Service> = services
+            .iter()
+            .filter(|s| results.iter().any(|r| r.name == s.name && r.ok))
+            .cloned()
+            .collect();
+        if !built.is_empty() {
+            println!("{}\n", "Starting services...".yellow().bold());
+            let started = run_with_progress(built, &["up", "-d"], legacy);
+            summarize(&started, "services started");
+            results.extend(started);
+        }
+    }
+
+    results
+}
+
+fn build_services(services: &[Service], config: &Config, pick: &Pick) {
+    let buildable: Vec<Service> = services.iter().filter(|s| s.has_build()).cloned().collect();
+
+    if buildable.is_empty() {
+        println!("\n{}", "No services with a build section found.".yellow());
+        pause();
+        return;
+    }
+
+    let selected = select_services(&buildable, pick);
+
+    if selected.is_empty() {
+        return;
+    }
+
+    let start = match interactive_menu(
+        "Build",
+        &[
+            ("b", "Build images"),
+            ("u", "Rebuild and start (up -d)"),
+            ("q", "Back"),
+        ],
+    ) {
+        Some('b') => false,
+        Some('u') => true,
+        _ => return,
+    };
+
+    let options = BuildOptions {
+        no_cache: confirm("Ignore build cache (--no-cache)?"),
+        pull: confirm("Pull newer base images (--pull)?"),
+    };
+
+    println!("\n{}\n", "Building images...".yellow().bold());
+    let results = build_and_start(&selected, &options, start, config.legacy_compose);
+    review_failures(&results);
 }
 
 fn show_status(services: &[Service], config: &Config) {
     clear_screen();
     println!("\n{}\n", "Services Status:".bold().cyan());
 
-    for (name, up) in check_all_statuses(services, config.legacy_compose) {
-        let status_text = if up { "UP".green() } else { "DOWN".red() };
-        println!("  {}: {}", name.cyan(), status_text);
+    for status in current_statuses(services, config.legacy_compose) {
+        print_service_status(&status);
     }
 
     println!();
     pause();
 }
 
-fn show_logs(services: &[Service], config: &Config) {
-    let service_names: Vec<&str> = services
-        .iter()
-        .map(|s| s.name.as_str())
-        .chain(std::iter::once("All"))
+fn print_service_status(status: &ServiceStatus) {
+    if let Some(error) = &status.error {
+        println!(
+            "  {}: {} {}",
+            status.name.cyan(),
+            state_label(status.state),
+            error.bright_black()
+        );
+        return;
+    }
+    if status.state == State::Down {
+        println!("  {}: {}", status.name.cyan(), state_label(status.state));
+        return;
+    }
+
+    let counts = format!(
+        "({}/{} running)",
+        status.running_count(),
+        status.containers.len()
+    );
+    println!(
+        "  {}: {} {}",
+        status.name.cyan(),
+        state_label(status.state),
+        counts.bright_black()
+    );
+
+    if status.state == State::Up {
+        return;
+    }
+
+    for container in status.containers.iter().filter(|c| !c.is_ok()) {
+        let detail = if container.is_running() {
+            container.health.clone()
+        } else {
+            format!("{} (exit code {})", container.state, container.exit_code)
+        };
+        println!(
+            "      {} {}",
+            container.name.bright_black(),
+            detail.yellow()
+        );
+    }
+}
+
+fn show_ports(services: &[Service], config: &Config) {
+    clear_screen();
+    println!("\n{}\n", "Port Overview:".bold().cyan());
+
+    let running: Vec<String> = current_statuses(services, config.legacy_compose)
+        .into_iter()
+        .filter(|s| s.running)
+        .map(|s| s.name)
         .collect();
 
-    if let Ok(selected) = Select::new("Which service?", service_names).prompt() {
-        println!("\n{}\n", "Streaming logs (Ctrl+C to return to menu)...".yellow());
+    for service in services {
+        let bindings = published_ports(service);
+        let marker = if running.contains(&service.name) {
+            "UP".green()
+        } else {
+            "DOWN".red()
+        };
+        if bindings.is_empty() {
+            println!(
+                "  {} {} {}",
+                service.name.cyan(),
+                marker,
+                "(no published ports)".bright_black()
+            );
+            continue;
+        }
+        println!("  {} {}", service.name.cyan(), marker);
+        for binding in bindings {
+            println!(
+                "      {} {}",
+                binding.label(),
+                format!("({})", binding.compose_service).bright_black()
+            );
+        }
+    }
+
+    let conflicts = find_conflicts(services, &running);
+    if !conflicts.is_empty() {
+        println!("\n{}", "Conflicts:".yellow().bold());
+        for conflict in &conflicts {
+            println!("  {}", describe_conflict(conflict).yellow());
+        }
+    }
 
-        if selected == "All" {
-            for service in services {
-                stream_logs(service, config.legacy_compose);
+    println!();
+    pause();
+}
+
+fn show_logs(services: &[Service], config: &Config, pick: &Pick) {
+    let selected = select_services(services, pick);
+
+    if selected.is_empty() {
+        return;
+    }
+
+    match interactive_menu(
+        "Logs",
+        &[
+            ("f", "Follow"),
+            ("o", "Follow with options (tail, since, timestamps)"),
+            ("s", "Save snapshot to file"),
+            ("q", "Back"),
+        ],
+    ) {
+        Some('f') => follow_logs(&selected, config, &LogOptions::default()),
+        Some('o') => {
+            if let Some(options) = prompt_log_options() {
+                follow_logs(&selected, config, &options);
+            }
+        }
+        Some('s') => {
+            if let Some(options) = prompt_log_options() {
+                println!("\n{}", "Collecting logs...".yellow());
+                match save_logs(&selected, config.legacy_compose, &options) {
+                    Ok(path) => println!("{}", format!("Logs saved to {}", path.display()).green()),
+                    Err(e) => println!("{}", format!("Error saving logs: {}", e).red()),
+                }
+                pause();
             }
-        } else if let Some(service) = services.iter().find(|s| s.name == selected) {
-            stream_logs(service, config.legacy_compose);
         }
+        _ => {}
     }
 }
 
-fn stream_logs(service: &Service, legacy: bool) {
-    let mut child = match build_compose_cmd(service, legacy)
-        .args(&["logs", "-f"])
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-    {
-        Ok(c) => c,
+fn follow_logs(selected: &[Service], config: &Config, options: &LogOptions) {
+    println!(
+        "\n{}\n",
+        "Streaming logs (Ctrl+C to return to menu)...".yellow()
+    );
+    stream_logs(selected, config.legacy_compose, options);
+}
+
+fn prompt_log_options() -> Option<LogOptions> {
+    println!();
+    let tail = inquire::Text::new("Lines of history per service (leave empty for all):")
+        .prompt()
+        .ok()?;
+    let tail = if tail.trim().is_empty() {
+        None
+    } else if let Ok(n) = tail.trim().parse::<usize>() {
+        Some(n)
+    } else {
+        println!("{}", "Invalid number!".red());
+        pause();
+        return None;
+    };
+
+    let since = inquire::Text::new("Since (e.g. 10m, 2h, 2024-01-01T10:00; leave empty for all):")
+        .prompt()
+        .ok()?;
+    let since = Some(since.trim().to_string()).filter(|s| !s.is_empty());
+
+    let timestamps = confirm("Show timestamps?");
+
+    Some(LogOptions {
+        tail,
+        since,
+        timestamps,
+    })
+}
+
+/// Prefers bash when the image has it.
+const SHELL_COMMAND: &str = "command -v bash >/dev/null 2>&1 && exec bash || exec sh";
+
+fn open_shell(services: &[Service], config: &Config) {
+    print!("{}", "Checking service status...".bright_black());
+    let _ = std::io::stdout().flush();
+    let statuses: Vec<ServiceStatus> = current_statuses(services, config.legacy_compose)
+        .into_iter()
+        .filter(|s| s.running)
+        .collect();
+    println!();
+
+    if statuses.is_empty() {
+        println!("{}", "\nNo services are currently running.".yellow());
+        pause();
+        return;
+    }
+
+    let names: Vec<String> = statuses.iter().map(|s| s.name.clone()).collect();
+    let status = match Select::new("Which service?", names).raw_prompt() {
+        Ok(selected) => &statuses[selected.index],
         Err(_) => return,
     };
+    let service = match services.iter().find(|s| s.name == status.name) {
+        Some(service) => service,
+        None => return,
+    };
 
-    let child_stdout = child.stdout.take().unwrap();
-    let child_stderr = child.stderr.take().unwrap();
+    // (label, compose service, replica index)
+    let mut targets: Vec<(String, String, Option<String>)> = status
+        .containers
+        .iter()
+        .filter(|c| c.is_running() && !c.service.is_empty())
+        .map(|c| {
+            let replicas = status
+                .containers
+                .iter()
+                .filter(|o| o.service == c.service)
+                .count();
+            let index = c
+                .name
+                .rsplit(['-', '_'])
+                .next()
+                .filter(|i| replicas > 1 && i.parse::<usize>().is_ok())
+                .map(|i| i.to_string());
+            (
+                format!("{} ({})", c.service, c.name),
+                c.service.clone(),
+                index,
+            )
+        })
+        .collect();
 
-    // Relay stdout — use \r\n so lines display correctly when raw mode is active
-    let t1 = thread::spawn(move || {
-        let reader = BufReader::new(child_stdout);
-        let stdout = std::io::stdout();
-        for line in reader.lines().flatten() {
-            let mut out = stdout.lock();
-            let _ = write!(out, "{}\r\n", line);
-        }
-    });
+    // docker-compose v1 statuses carry no compose service names
+    if targets.is_empty() {
+        targets = get_compose_services(service, config.legacy_compose)
+            .into_iter()
+            .map(|name| (name.clone(), name, None))
+            .collect();
+    }
 
-    let t2 = thread::spawn(move || {
-        let reader = BufReader::new(child_stderr);
-        let stderr = std::io::stderr();
-        for line in reader.lines().flatten() {
-            let mut err = stderr.lock();
-            let _ = write!(err, "{}\r\n", line);
+    let target = if targets.len() == 1 {
+        &targets[0]
+    } else {
+        let labels: Vec<String> = targets.iter().map(|t| t.0.clone()).collect();
+        match Select::new("Which container?", labels).raw_prompt() {
+            Ok(selected) => &targets[selected.index],
+            Err(_) => return,
         }
+    };
+
+    let mut cmd = build_compose_cmd(service, config.legacy_compose);
+    cmd.arg("exec");
+    if let Some(index) = &target.2 {
+        cmd.args(["--index", index]);
+    }
+    cmd.args([target.1.as_str(), "sh", "-c", SHELL_COMMAND]);
+
+    let result = with_terminal_released(|| {
+        println!(
+            "{}",
+            format!(
+                "Opening shell in {} / {} (exit to return)...",
+                service.name, target.0
+            )
+            .yellow()
+        );
+        cmd.status()
     });
 
-    // Watch for Ctrl+C / Esc in raw mode while child runs
-    let _ = enable_raw_mode();
+    // Any other exit code is just whatever the shell's last command returned
+    let failure = match result {
+        Err(e) => Some(e.to_string()),
+        Ok(status) => match status.code() {
+            Some(code @ (126 | 127)) => Some(format!("no usable shell (exit code {})", code)),
+            _ => None,
+        },
+    };
+    if let Some(reason) = failure {
+        println!(
+            "\n{}",
+            format!("Couldn't open a shell in {}: {}", target.0, reason).red()
+        );
+        pause();
+    }
+}
+
+fn cleanup_data(services: &[Service], config: &Config, pick: &Pick) {
+    let selected = select_services(services, pick);
+
+    if selected.is_empty() {
+        return;
+    }
+
+    if confirm(&format!("Remove volumes from {} service(s)?", selected.len())) {
+        println!("\n{}", "Removing volumes...".red());
+        let results = run_with_progress(selected, &["down", "-v"], config.legacy_compose);
+        summarize(&results, "volumes removed");
+        review_failures(&results);
+    } else {
+        pause();
+    }
+}
+
+fn manage_snapshots(services: &[Service], config: &Config) {
     loop {
-        if let Ok(true) = poll(std::time::Duration::from_millis(50)) {
-            if let Ok(Event::Key(key)) = read() {
-                let ctrl_c = key.code == KeyCode::Char('c')
-                    && key.modifiers.contains(KeyModifiers::CONTROL);
-                if ctrl_c || key.code == KeyCode::Esc {
-                    child.kill().ok();
-                    break;
+        clear_screen();
+        let snapshots = list_snapshots();
+        println!("\n{}", "Saved snapshots:".bold());
+        if snapshots.is_empty() {
+            println!("  {}", "None".yellow());
+        } else {
+            for (name, snapshot) in &snapshots {
+                println!(
+                    "  {} {}: {}",
+                    name.cyan(),
+                    format!("({})", snapshot.created).bright_black(),
+                    snapshot.running.join(", ").yellow()
+                );
+            }
+        }
+
+        match interactive_menu(
+            "Workspace Snapshots",
+            &[
+                ("s", "Save running services"),
+                ("r", "Restore snapshot"),
+                ("d", "Delete snapshot"),
+                ("q", "Back"),
+            ],
+        ) {
+            Some('s') => {
+                println!();
+                let name = match inquire::Text::new("Snapshot name:").prompt() {
+                    Ok(name) => name.trim().to_string(),
+                    Err(_) => continue,
+                };
+                if let Err(e) = validate_name(&name) {
+                    println!("{}", format!("{}!", e).red());
+                    pause();
+                    continue;
                 }
+                save_and_stop(&name, services, config);
             }
+            Some('r') => {
+                if snapshots.is_empty() {
+                    println!("\n{}", "No snapshots to restore!".yellow());
+                    pause();
+                    continue;
+                }
+
+                println!();
+                let names: Vec<String> = snapshots.iter().map(|(n, _)| n.clone()).collect();
+                if let Ok(selected) = Select::new("Select snapshot to restore:", names).prompt() {
+                    if let Some((_, snapshot)) = snapshots.iter().find(|(n, _)| *n == selected) {
+                        restore_snapshot(snapshot, services, config);
+                    }
+                }
+            }
+            Some('d') => {
+                if snapshots.is_empty() {
+                    println!("\n{}", "No snapshots to delete!".yellow());
+                    pause();
+                    continue;
+                }
+
+                println!();
+                let names: Vec<String> = snapshots.into_iter().map(|(n, _)| n).collect();
+                if let Ok(selected) = Select::new("Select snapshot to delete:", names).prompt() {
+                    if confirm(&format!("Delete snapshot {}?", selected)) {
+                        match delete_snapshot(&selected) {
+                            Ok(()) => {
+                                println!("{}", format!("Snapshot {} deleted!", selected).green())
+                            }
+                            Err(e) => println!("{}", e.red()),
+                        }
+                        pause();
+                    }
+                }
+            }
+            _ => break,
+        }
+    }
+}
+
+/// Records what is running under `name`, then offers to stop it all.
+fn save_and_stop(name: &str, services: &[Service], config: &Config) {
+    let statuses = current_statuses(services, config.legacy_compose);
+    let snapshot = match save_snapshot(name, &statuses) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            println!("{}", format!("Failed to save snapshot: {}", e).red());
+            pause();
+            return;
+        }
+    };
+    println!("{}", format!("Snapshot {} saved!", name).green());
+
+    let running: Vec<Service> = services
+        .iter()
+        .filter(|s| snapshot.running.contains(&s.name))
+        .cloned()
+        .collect();
+    if running.is_empty() {
+        println!(
+            "{}",
+            "Nothing is running, so the snapshot is empty.".yellow()
+        );
+        pause();
+        return;
+    }
+
+    let question = format!(
+        "Recorded:\n{}\n\nStop these {} service(s) now?",
+        snapshot
+            .running
+            .iter()
+            .map(|n| format!("  - {}", n))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        running.len()
+    );
+    if !confirm(&question) {
+        return;
+    }
+
+    println!("\n{}\n", "Stopping services...".yellow());
+    let graph = DependencyGraph::build(services, config);
+    match run_ordered(
+        &running,
+        &graph,
+        Direction::Stop,
+        &["down"],
+        config.legacy_compose,
+    ) {
+        Ok(results) => {
+            summarize(&results, "services stopped");
+            review_failures(&results);
         }
-        match child.try_wait() {
-            Ok(Some(_)) => break,
-            Ok(None) => {}
-            Err(_) => break,
+        Err(e) => {
+            println!("{}\n", e.red());
+            pause();
         }
     }
-    let _ = disable_raw_mode();
-    let _ = child.wait();
-    let _ = t1.join();
-    let _ = t2.join();
 }
 
-fn cleanup_data(services: &[Service], config: &Config) {
-    let selected = select_services(services);
+/// Shows what restoring would change, then stops the extras and starts
+/// what's missing, each in dependency order.
+fn restore_snapshot(snapshot: &Snapshot, services: &[Service], config: &Config) {
+    let statuses = current_statuses(services, config.legacy_compose);
+    let plan = restore_plan(snapshot, services, &statuses);
 
-    if selected.is_empty() {
+    for name in &plan.missing {
+        println!(
+            "{}",
+            format!("Warning: {} is in the snapshot but wasn't found", name).yellow()
+        );
+    }
+    if plan.is_empty() {
+        println!(
+            "\n{}",
+            "Running services already match the snapshot.".green()
+        );
+        pause();
         return;
     }
 
-    if confirm(&format!("Remove volumes from {} service(s)?", selected.len())) {
-        println!("\n{}", "Removing volumes...".red());
-        let results = run_parallel(selected, &["down", "-v"], config.legacy_compose);
-        for (name, ok) in &results {
-            print_result(name, *ok);
+    let list = |selected: &[Service]| {
+        selected
+            .iter()
+            .map(|s| format!("  - {}", s.name))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let mut sections = Vec::new();
+    if !plan.to_stop.is_empty() {
+        sections.push(format!("Services to stop:\n{}", list(&plan.to_stop)));
+    }
+    if !plan.to_start.is_empty() {
+        sections.push(format!("Services to start:\n{}", list(&plan.to_start)));
+    }
+    if !confirm(&format!("{}\n\nRestore snapshot?", sections.join("\n\n"))) {
+        return;
+    }
+
+    let graph = DependencyGraph::build(services, config);
+    let mut results = Vec::new();
+    for (selected, args, direction, message) in [
+        (
+            &plan.to_stop,
+            &["down"][..],
+            Direction::Stop,
+            "Stopping services...",
+        ),
+        (
+            &plan.to_start,
+            &["up", "-d"][..],
+            Direction::Start,
+            "Starting services...",
+        ),
+    ] {
+        if selected.is_empty() {
+            continue;
+        }
+        println!("\n{}\n", message.yellow());
+        match run_ordered(selected, &graph, direction, args, config.legacy_compose) {
+            Ok(step) => results.extend(step),
+            Err(e) => {
+                println!("{}\n", e.red());
+                pause();
+                return;
+            }
         }
-        let ok_count = results.iter().filter(|(_, ok)| *ok).count();
-        println!("\n{}\n", format!("{}/{} volumes removed", ok_count, results.len()).green());
     }
 
-    pause();
+    summarize(&results, "services restored");
+    review_failures(&results);
 }
 
-pub fn show_settings(config: &mut Config) {
+pub fn show_settings(services: &[Service], config: &mut Config) {
     loop {
         clear_screen();
         println!("\n{}", "Current configuration:".bold());
         println!(
-            "  Max search depth: {}",
+            "  Max search depth: {} {}",
             config
                 .max_depth
                 .map(|d| d.to_string())
                 .unwrap_or_else(|| "Unlimited".to_string())
-                .yellow()
+                .yellow(),
+            source_tag(config, "max_depth")
         );
         println!(
-            "  Excluded dirs: {}",
+            "  Excluded dirs: {} {}",
             if config.excluded_dirs.is_empty() {
                 "None".to_string()
             } else {
                 config.excluded_dirs.join(", ")
             }
-            .yellow()
+            .yellow(),
+            source_tag(config, "excluded_dirs")
         );
         println!(
-            "  Docker command: {}",
+            "  Docker command: {} {}",
             if config.legacy_compose {
                 "docker-compose"
             } else {
                 "docker compose"
             }
-            .yellow()
+            .yellow(),
+            source_tag(config, "legacy_compose")
+        );
+        println!(
+            "  Max parallel operations: {} {}",
+            match config.max_parallel {
+                0 => "Unlimited".to_string(),
+                max => max.to_string(),
+            }
+            .yellow(),
+            source_tag(config, "max_parallel")
+        );
+        println!(
+            "  Wait until healthy after start: {} {}",
+            if config.wait_healthy {
+                format!("Yes (up to {}s)", config.health_timeout)
+            } else {
+                "No".to_string()
+            }
+            .yellow(),
+            source_tag(config, "wait_healthy")
+        );
+        println!(
+            "  Operation timeout: {} {}",
+            match config.operation_timeout {
+                0 => "None".to_string(),
+                secs => format!("{}s", secs),
+            }
+            .yellow(),
+            source_tag(config, "operation_timeout")
+        );
+        println!(
+            "  Service groups: {} {}",
+            if config.groups.is_empty() {
+                "None".to_string()
+            } else {
+                config
+                    .groups
+                    .keys()
+                    .map(|g| format!("@{}", g))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+            .yellow(),
+            source_tag(config, "groups")
+        );
+        println!(
+            "\n  Saving to: {}",
+            config.layers.save_path().display().to_string().cyan()
         );
 
         match interactive_menu(
@@ -324,7 +1448,16 @@ pub fn show_settings(config: &mut Config) {
             &[
                 ("d", "Set max search depth"),
                 ("e", "Manage excluded directories"),
+                ("g", "Manage service groups"),
                 ("c", "Toggle docker command (docker compose / docker-compose)"),
+                ("j", "Set max parallel operations"),
+                ("t", "Set operation timeout"),
+                ("w", "Toggle waiting until healthy after start"),
+                ("h", "Set health wait timeout"),
+                (
+                    "l",
+                    "Toggle where settings are saved (global / project file)",
+                ),
                 ("r", "Reset to defaults"),
                 ("q", "Back"),
             ],
@@ -350,6 +1483,9 @@ pub fn show_settings(config: &mut Config) {
             Some('e') => {
                 manage_excluded_dirs(config);
             }
+            Some('g') => {
+                manage_groups(services, config);
+            }
             Some('c') => {
                 config.legacy_compose = !config.legacy_compose;
                 let cmd = if config.legacy_compose {
@@ -361,9 +1497,72 @@ pub fn show_settings(config: &mut Config) {
                 println!("{}", format!("Docker command set to: {}", cmd).green());
                 pause();
             }
+            Some('j') => {
+                println!();
+                if let Ok(input) =
+                    inquire::Text::new("Max parallel operations (0 for unlimited):").prompt()
+                {
+                    match input.trim().parse::<usize>() {
+                        Ok(max) => {
+                            config.max_parallel = max;
+                            save_with_feedback(config);
+                        }
+                        Err(_) => println!("{}", "Invalid number!".red()),
+                    }
+                }
+                pause();
+            }
+            Some('t') => {
+                println!();
+                if let Ok(input) =
+                    inquire::Text::new("Operation timeout in seconds (0 for none):").prompt()
+                {
+                    match input.trim().parse::<u64>() {
+                        Ok(secs) => {
+                            config.operation_timeout = secs;
+                            save_with_feedback(config);
+                        }
+                        Err(_) => println!("{}", "Invalid number!".red()),
+                    }
+                }
+                pause();
+            }
+            Some('w') => {
+                config.wait_healthy = !config.wait_healthy;
+                save_with_feedback(config);
+                pause();
+            }
+            Some('h') => {
+                println!();
+                if let Ok(input) = inquire::Text::new("Health wait timeout in seconds:").prompt() {
+                    match input.trim().parse::<u64>() {
+                        Ok(secs) if secs > 0 => {
+                            config.health_timeout = secs;
+                            save_with_feedback(config);
+                        }
+                        _ => println!("{}", "Invalid number!".red()),
+                    }
+                }
+                pause();
+            }
+            Some('l') => {
+                config.layers.toggle_save_target();
+                println!(
+                    "{}",
+                    format!(
+                        "Settings will be saved to {}",
+                        config.layers.save_path().display()
+                    )
+                    .green()
+                );
+                pause();
+            }
             Some('r') => {
                 if confirm("Reset all settings to defaults?") {
-                    *config = Config::default();
+                    *config = Config {
+                        layers: config.layers.clone(),
+                        ..Config::default()
+                    };
                     save_with_feedback(config);
                     println!("{}", "Settings reset to defaults!".green());
                     pause();
@@ -371,6 +1570,102 @@ pub fn show_settings(config: &mut Config) {
             }
             _ => break,
         }
+        apply_limits(config);
+        set_tracker_legacy(config.legacy_compose);
+    }
+}
+
+/// Which config layer a setting's value comes from, e.g. "(global)".
+fn source_tag(config: &Config, key: &str) -> ColoredString {
+    format!("({})", config.layers.source(key)).bright_black()
+}
+
+fn manage_groups(services: &[Service], config: &mut Config) {
+    loop {
+        clear_screen();
+        println!("\n{}", "Service groups:".bold());
+        if config.groups.is_empty() {
+            println!("  {}", "None".yellow());
+        } else {
+            for (name, members) in &config.groups {
+                println!("  @{}: {}", name.cyan(), members.join(", ").yellow());
+            }
+        }
+
+        match interactive_menu(
+            "Manage Service Groups",
+            &[
+                ("a", "Add or edit group"),
+                ("r", "Remove group"),
+                ("q", "Back"),
+            ],
+        ) {
+            Some('a') => {
+                println!();
+                let name = match inquire::Text::new("Group name:").prompt() {
+                    Ok(name) => name.trim().trim_start_matches('@').to_string(),
+                    Err(_) => continue,
+                };
+                if name.is_empty() || name.contains(char::is_whitespace) {
+                    println!("{}", "Group names can't be empty or contain spaces!".red());
+                    pause();
+                    continue;
+                }
+
+                let labels: Vec<String> = services.iter().map(|s| s.name.clone()).collect();
+                let current = config.groups.get(&name).cloned().unwrap_or_default();
+                // Members outside this scan (depth, exclusions) can't be picked
+                // here, but stay in the group
+                let undiscovered: Vec<String> = current
+                    .iter()
+                    .filter(|m| !services.iter().any(|s| &s.name == *m))
+                    .cloned()
+                    .collect();
+                if !undiscovered.is_empty() {
+                    println!(
+                        "{}",
+                        format!("Kept, not found here: {}", undiscovered.join(", ")).bright_black()
+                    );
+                }
+                let defaults: Vec<usize> = services
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| current.contains(&s.name))
+                    .map(|(i, _)| i)
+                    .collect();
+                let members = MultiSelect::new(&format!("Services in @{}:", name), labels)
+                    .with_default(&defaults)
+                    .prompt();
+                match members {
+                    Ok(mut members) if !members.is_empty() || !undiscovered.is_empty() => {
+                        members.extend(undiscovered);
+                        config.groups.insert(name.clone(), members);
+                        save_with_feedback(config);
+                        println!("{}", format!("Group @{} saved!", name).green());
+                    }
+                    Ok(_) => println!("{}", "A group needs at least one service.".yellow()),
+                    Err(_) => continue,
+                }
+                pause();
+            }
+            Some('r') => {
+                if config.groups.is_empty() {
+                    println!("\n{}", "No groups to remove!".yellow());
+                    pause();
+                    continue;
+                }
+
+                println!();
+                let names: Vec<String> = config.groups.keys().cloned().collect();
+                if let Ok(selected) = Select::new("Select group to remove:", names).prompt() {
+                    config.groups.remove(&selected);
+                    save_with_feedback(config);
+                    println!("{}", format!("Group @{} removed!", selected).green());
+                    pause();
+                }
+            }
+            _ => break,
+        }
     }
 }
 
@@ -0,0 +1,418 @@
+use crate::service::Service;
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{TcpListener, UdpSocket};
+
+/// A host port published by one compose service of a stack.
+#[derive(Clone, Debug)]
+pub struct PortBinding {
+    pub compose_service: String,
+    pub host_ip: String,
+    pub host_port: u16,
+    pub container_port: String,
+    pub protocol: String,
+}
+
+impl PortBinding {
+    fn overlaps(&self, other: &PortBinding) -> bool {
+        let wildcard = |ip: &str| ip.is_empty() || ip == "0.0.0.0" || ip == "::";
+        self.host_port == other.host_port
+            && self.protocol == other.protocol
+            && (wildcard(&self.host_ip)
+                || wildcard(&other.host_ip)
+                || self.host_ip == other.host_ip)
+    }
+
+    pub fn label(&self) -> String {
+        let host = if self.host_ip.is_empty() {
+            self.host_port.to_string()
+        } else {
+            format!("{}:{}", self.host_ip, self.host_port)
+        };
+        format!("{}->{}/{}", host, self.container_port, self.protocol)
+    }
+}
+
+#[derive(Debug)]
+pub struct PortConflict {
+    pub port: u16,
+    pub protocol: String,
+    /// (stack, compose service) pairs that want the port
+    pub claimants: Vec<(String, String)>,
+    /// Something outside the selection already listens on it
+    pub in_use_on_host: bool,
+}
+
+/// Variables from a compose `.env` file: `KEY=value` lines, optionally
+/// quoted or prefixed with `export`.
+fn parse_dotenv(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let quoted = ['"', '\''].into_iter().find_map(|q| {
+                value
+                    .strip_prefix(q)
+                    .and_then(|v| v.split_once(q))
+                    .map(|(inner, _)| inner)
+            });
+            let value = quoted.unwrap_or_else(|| match value.split_once(" #") {
+                Some((value, _)) => value.trim_end(),
+                None => value,
+            });
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Resolves `${VAR}` / `${VAR:-default}` the way compose would, from the
+/// environment and then the project's `.env`; None if unset.
+fn interpolate(value: &str, dotenv: &HashMap<String, String>) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find('}')? + start;
+        let expr = &rest[start + 2..end];
+        let (name, default) = match expr.split_once(":-").or_else(|| expr.split_once('-')) {
+            Some((name, default)) => (name, Some(default)),
+            None => (expr, None),
+        };
+        let resolved = std::env::var(name)
+            .ok()
+            .or_else(|| dotenv.get(name).cloned())
+            .filter(|v| !v.is_empty())
+            .or_else(|| default.map(|d| d.to_string()))?;
+        out.push_str(&resolved);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+/// Expands "8080" or "3000-3002" into individual ports.
+fn parse_range(value: &str) -> Vec<u16> {
+    match value.split_once('-') {
+        Some((from, to)) => match (from.parse::<u16>(), to.parse::<u16>()) {
+            (Ok(from), Ok(to)) if from <= to => (from..=to).collect(),
+            _ => Vec::new(),
+        },
+        None => value.parse::<u16>().into_iter().collect(),
+    }
+}
+
+/// Short syntax: "[host_ip:][host_port:]container_port[/protocol]".
+fn parse_short(
+    compose_service: &str,
+    spec: &str,
+    dotenv: &HashMap<String, String>,
+) -> Vec<PortBinding> {
+    let spec = match interpolate(spec, dotenv) {
+        Some(spec) => spec,
+        None => return Vec::new(),
+    };
+    let (spec, protocol) = match spec.split_once('/') {
+        Some((spec, protocol)) => (spec.to_string(), protocol.to_string()),
+        None => (spec, "tcp".to_string()),
+    };
+
+    // IPv6 host IPs come bracketed: "[::1]:8080:80"
+    let (host_ip, rest) = match spec.strip_prefix('[').and_then(|s| s.split_once("]:")) {
+        Some((ip, rest)) => (ip.to_string(), rest.to_string()),
+        None => (String::new(), spec.clone()),
+    };
+    let parts: Vec<&str> = rest.split(':').collect();
+    let (host_ip, host, container) = match parts.as_slice() {
+        [ip, host, container] => (ip.to_string(), *host, *container),
+        [host, container] => (host_ip, *host, *container),
+        // Container port only: docker picks a random host port
+        _ => return Vec::new(),
+    };
+
+    let host_ports = parse_range(host);
+    let container_ports = parse_range(container);
+    host_ports
+        .iter()
+        .enumerate()
+        .map(|(i, port)| PortBinding {
+            compose_service: compose_service.to_string(),
+            host_ip: host_ip.clone(),
+            host_port: *port,
+            container_port: container_ports
+                .get(i)
+                .map(|p| p.to_string())
+                .unwrap_or_else(|| container.to_string()),
+            protocol: protocol.clone(),
+        })
+        .collect()
+}
+
+/// Long syntax: `{ target, published, host_ip, protocol }`.
+fn parse_long(
+    compose_service: &str,
+    entry: &Value,
+    dotenv: &HashMap<String, String>,
+) -> Vec<PortBinding> {
+    let field = |key: &str| match entry.get(key) {
+        Some(Value::String(s)) => interpolate(s, dotenv),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    };
+    let published = match field("published") {
+        Some(published) => published,
+        None => return Vec::new(),
+    };
+    let target = field("target").unwrap_or_default();
+    let host_ip = field("host_ip").unwrap_or_default();
+    let protocol = field("protocol").unwrap_or_else(|| "tcp".to_string());
+
+    parse_range(&published)
+        .into_iter()
+        .map(|port| PortBinding {
+            compose_service: compose_service.to_string(),
+            host_ip: host_ip.clone(),
+            host_port: port,
+            container_port: target.clone(),
+            protocol: protocol.clone(),
+        })
+        .collect()
+}
+
+/// Host ports published by a stack, read from its compose file.
+pub fn published_ports(service: &Service) -> Vec<PortBinding> {
+    let compose = match service.load_compose() {
+        Some(compose) => compose,
+        None => return Vec::new(),
+    };
+    let services = match compose.get("services").and_then(|s| s.as_mapping()) {
+        Some(services) => services,
+        None => return Vec::new(),
+    };
+    let dotenv = fs::read_to_string(service.path.join(".env"))
+        .map(|contents| parse_dotenv(&contents))
+        .unwrap_or_default();
+
+    let mut bindings = Vec::new();
+    for (name, definition) in services {
+        let name = name.as_str().unwrap_or_default();
+        let ports = match definition.get("ports").and_then(|p| p.as_sequence()) {
+            Some(ports) => ports,
+            None => continue,
+        };
+        for entry in ports {
+            match entry {
+                Value::String(spec) => bindings.extend(parse_short(name, spec, &dotenv)),
+                Value::Number(n) => bindings.extend(parse_short(name, &n.to_string(), &dotenv)),
+                Value::Mapping(_) => bindings.extend(parse_long(name, entry, &dotenv)),
+                _ => {}
+            }
+        }
+    }
+    bindings
+}
+
+fn port_in_use(binding: &PortBinding) -> bool {
+    let ip = if binding.host_ip.is_empty() {
+        "0.0.0.0"
+    } else {
+        binding.host_ip.as_str()
+    };
+    let addr = if ip.contains(':') {
+        format!("[{}]:{}", ip, binding.host_port)
+    } else {
+        format!("{}:{}", ip, binding.host_port)
+    };
+    // Other errors, like EACCES for ports below 1024, say nothing about
+    // whether the port is taken
+    let error = match binding.protocol.as_str() {
+        "udp" => UdpSocket::bind(&addr).err(),
+        _ => TcpListener::bind(&addr).err(),
+    };
+    matches!(error, Some(e) if e.kind() == ErrorKind::AddrInUse)
+}
+
+/// Ports that stacks about to start would fight over, either with each other
+/// or with something already listening on the host. Stacks in `running` are
+/// skipped for the host check since their own containers hold their ports.
+pub fn find_conflicts(services: &[Service], running: &[String]) -> Vec<PortConflict> {
+    let bindings: Vec<(&Service, PortBinding)> = services
+        .iter()
+        .flat_map(|s| published_ports(s).into_iter().map(move |b| (s, b)))
+        .collect();
+
+    let mut conflicts: Vec<PortConflict> = Vec::new();
+    for (i, (service, binding)) in bindings.iter().enumerate() {
+        let already = conflicts
+            .iter()
+            .any(|c| c.port == binding.host_port && c.protocol == binding.protocol);
+        if already {
+            continue;
+        }
+
+        let mut claimants: Vec<(String, String)> = Vec::new();
+        for (s, b) in bindings[i..].iter().filter(|(_, b)| binding.overlaps(b)) {
+            let claimant = (s.name.clone(), b.compose_service.clone());
+            if !claimants.contains(&claimant) {
+                claimants.push(claimant);
+            }
+        }
+        let in_use_on_host = !running.contains(&service.name) && port_in_use(binding);
+
+        if claimants.len() > 1 || in_use_on_host {
+            conflicts.push(PortConflict {
+                port: binding.host_port,
+                protocol: binding.protocol.clone(),
+                claimants,
+                in_use_on_host,
+            });
+        }
+    }
+    conflicts
+}
+
+impl PortConflict {
+    pub fn involves(&self, service: &Service) -> bool {
+        self.claimants
+            .iter()
+            .any(|(stack, _)| stack == &service.name)
+    }
+}
+
+pub fn describe_conflict(conflict: &PortConflict) -> String {
+    let claimants: Vec<String> = conflict
+        .claimants
+        .iter()
+        .map(|(stack, service)| format!("{}/{}", stack, service))
+        .collect();
+    let mut text = format!(
+        "port {}/{}: {}",
+        conflict.port,
+        conflict.protocol,
+        claimants.join(", ")
+    );
+    if conflict.in_use_on_host {
+        text.push_str(" (already in use on this host)");
+    }
+    text
+}
+
+/// Conflicts that starting `selected` would run into. Running stacks are
+/// included so a clash with one of them is reported by name.
+pub fn conflicts_for_start(
+    selected: &[Service],
+    all: &[Service],
+    running: &[String],
+) -> Vec<PortConflict> {
+    let mut candidates = selected.to_vec();
+    candidates.extend(
+        all.iter()
+            .filter(|s| running.contains(&s.name) && !selected.iter().any(|x| x.name == s.name))
+            .cloned(),
+    );
+    find_conflicts(&candidates, running)
+        .into_iter()
+        .filter(|c| selected.iter().any(|s| c.involves(s)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn labels(bindings: &[PortBinding]) -> Vec<String> {
+        bindings.iter().map(|b| b.label()).collect()
+    }
+
+    #[test]
+    fn parses_short_syntax() {
+        let none = HashMap::new();
+        assert_eq!(
+            labels(&parse_short("web", "8080:80", &none)),
+            ["8080->80/tcp"]
+        );
+        assert_eq!(
+            labels(&parse_short("web", "127.0.0.1:53:53/udp", &none)),
+            ["127.0.0.1:53->53/udp"]
+        );
+        assert_eq!(
+            labels(&parse_short("web", "[::1]:8443:443", &none)),
+            ["::1:8443->443/tcp"]
+        );
+        assert!(parse_short("web", "80", &none).is_empty());
+    }
+
+    #[test]
+    fn expands_port_ranges() {
+        let bindings = parse_short("web", "3000-3002:4000-4002", &HashMap::new());
+        assert_eq!(
+            labels(&bindings),
+            ["3000->4000/tcp", "3001->4001/tcp", "3002->4002/tcp"]
+        );
+        assert!(parse_short("web", "3002-3000:80", &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn parses_long_syntax() {
+        let entry: Value =
+            serde_yaml::from_str("{ target: 80, published: \"8080\", host_ip: 0.0.0.0 }").unwrap();
+        let bindings = parse_long("web", &entry, &HashMap::new());
+        assert_eq!(labels(&bindings), ["0.0.0.0:8080->80/tcp"]);
+
+        let unpublished: Value = serde_yaml::from_str("{ target: 80 }").unwrap();
+        assert!(parse_long("web", &unpublished, &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn interpolates_from_dotenv_and_defaults() {
+        let dotenv = env(&[("DOCKERSTRATOR_TEST_PORT", "9000")]);
+        assert_eq!(
+            interpolate("${DOCKERSTRATOR_TEST_PORT}:80", &dotenv).as_deref(),
+            Some("9000:80")
+        );
+        assert_eq!(
+            interpolate("${DOCKERSTRATOR_TEST_UNSET:-8080}:80", &dotenv).as_deref(),
+            Some("8080:80")
+        );
+        assert_eq!(interpolate("${DOCKERSTRATOR_TEST_UNSET}:80", &dotenv), None);
+        assert_eq!(interpolate("8080:80", &dotenv).as_deref(), Some("8080:80"));
+    }
+
+    #[test]
+    fn short_syntax_uses_dotenv() {
+        let dotenv = env(&[("DOCKERSTRATOR_TEST_WEB", "8081")]);
+        let bindings = parse_short("web", "${DOCKERSTRATOR_TEST_WEB}:80", &dotenv);
+        assert_eq!(labels(&bindings), ["8081->80/tcp"]);
+    }
+
+    #[test]
+    fn parses_dotenv_files() {
+        let dotenv = parse_dotenv(
+            "# ports\nWEB=8080\nexport API = 9090\nDB=\"5432\" # postgres\nCACHE=6379 # redis\n\nQUOTED='a b'\n",
+        );
+        assert_eq!(
+            dotenv,
+            env(&[
+                ("WEB", "8080"),
+                ("API", "9090"),
+                ("DB", "5432"),
+                ("CACHE", "6379"),
+                ("QUOTED", "a b"),
+            ])
+        );
+    }
+}
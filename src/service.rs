@@ -2,6 +2,14 @@ use crate::config::Config;
 use inquire::Select;
 use std::{fs, path::PathBuf};
 
+/// Same precedence docker compose itself uses when no -f is given
+const DEFAULT_COMPOSE_FILES: [&str; 4] = [
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
 #[derive(Clone, Debug)]
 pub struct Service {
     pub name: String,
@@ -9,14 +17,33 @@ pub struct Service {
     pub compose_file: String,
 }
 
-pub fn find_services(config: &Config) -> Vec<Service> {
+impl Service {
+    /// Parsed compose file, or None if it can't be read or isn't valid YAML.
+    pub fn load_compose(&self) -> Option<serde_yaml::Value> {
+        let contents = fs::read_to_string(self.path.join(&self.compose_file)).ok()?;
+        serde_yaml::from_str(&contents).ok()
+    }
+
+    /// Whether any compose service in this stack has a `build:` section.
+    pub fn has_build(&self) -> bool {
+        self.load_compose()
+            .and_then(|compose| compose.get("services").cloned())
+            .and_then(|services| services.as_mapping().cloned())
+            .map(|services| services.values().any(|s| s.get("build").is_some()))
+            .unwrap_or(false)
+    }
+}
+
+/// Discovers compose projects below the current directory. When `prompt` is
+/// false, directories with several compose files never ask which one to use.
+pub fn find_services(config: &Config, prompt: bool) -> Vec<Service> {
     let current_dir = std::env::current_dir().unwrap();
     let mut services = Vec::new();
     let mut visited = std::collections::HashSet::new();
 
     let compose_files = get_compose_files(&current_dir);
     if !compose_files.is_empty() {
-        if let Some(compose_file) = select_compose_file(&compose_files) {
+        if let Some(compose_file) = select_compose_file(&compose_files, prompt) {
             services.push(Service {
                 name: "root".to_string(),
                 path: current_dir.clone(),
@@ -25,7 +52,7 @@ pub fn find_services(config: &Config) -> Vec<Service> {
         }
     }
 
-    scan_directory(&current_dir, &mut services, &mut visited, config, 0);
+    scan_directory(&current_dir, &mut services, &mut visited, config, 0, prompt);
     services.sort_by(|a, b| a.name.cmp(&b.name));
     services
 }
@@ -36,6 +63,7 @@ fn scan_directory(
     visited: &mut std::collections::HashSet<PathBuf>,
     config: &Config,
     depth: usize,
+    prompt: bool,
 ) {
     if visited.contains(dir) {
         return;
@@ -65,7 +93,7 @@ fn scan_directory(
                 let compose_files = get_compose_files(&path);
 
                 if !compose_files.is_empty() {
-                    if let Some(compose_file) = select_compose_file(&compose_files) {
+                    if let Some(compose_file) = select_compose_file(&compose_files, prompt) {
                         if let Some(service_name) = path.file_name().and_then(|n| n.to_str()) {
                             services.push(Service {
                                 name: service_name.to_string(),
@@ -75,7 +103,7 @@ fn scan_directory(
                         }
                     }
                 } else {
-                    scan_directory(&path, services, visited, config, depth + 1);
+                    scan_directory(&path, services, visited, config, depth + 1, prompt);
                 }
             }
         }
@@ -104,7 +132,7 @@ fn get_compose_files(dir: &PathBuf) -> Vec<String> {
     files
 }
 
-fn select_compose_file(files: &[String]) -> Option<String> {
+fn select_compose_file(files: &[String], prompt: bool) -> Option<String> {
     if files.is_empty() {
         return None;
     }
@@ -113,8 +141,15 @@ fn select_compose_file(files: &[String]) -> Option<String> {
         return Some(files[0].clone());
     }
 
-    match Select::new("Multiple compose files found. Which one to use?", files.to_vec()).prompt() {
-        Ok(selected) => Some(selected),
-        Err(_) => None,
+    if !prompt {
+        return DEFAULT_COMPOSE_FILES
+            .iter()
+            .find(|name| files.iter().any(|f| f == *name))
+            .map(|name| name.to_string())
+            .or_else(|| Some(files[0].clone()));
     }
+
+    Select::new("Multiple compose files found. Which one to use?", files.to_vec())
+        .prompt()
+        .ok()
 }
@@ -0,0 +1,199 @@
+use crate::docker::ServiceStatus;
+use crate::service::Service;
+use crate::state::{load_project, load_state, project_key, update_project};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+/// Which stacks were running when the snapshot was taken.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub created: String,
+    pub running: Vec<String>,
+}
+
+/// What restoring a snapshot takes from the current state.
+pub struct RestorePlan {
+    pub to_start: Vec<Service>,
+    pub to_stop: Vec<Service>,
+    /// Stacks in the snapshot that weren't discovered this time
+    pub missing: Vec<String>,
+}
+
+impl RestorePlan {
+    pub fn is_empty(&self) -> bool {
+        self.to_start.is_empty() && self.to_stop.is_empty()
+    }
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        Err("Snapshot names can't be empty or contain spaces".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Snapshots saved in the current target directory.
+pub fn list_snapshots() -> Vec<(String, Snapshot)> {
+    load_project().snapshots.into_iter().collect()
+}
+
+pub fn get_snapshot(name: &str) -> Result<Snapshot, String> {
+    let snapshots = load_project().snapshots;
+    snapshots.get(name).cloned().ok_or_else(|| {
+        // Restoring it here would stop stacks the snapshot knows nothing about
+        let current = project_key();
+        let elsewhere: Vec<String> = load_state()
+            .projects
+            .into_iter()
+            .filter(|(dir, project)| *dir != current && project.snapshots.contains_key(name))
+            .map(|(dir, _)| dir)
+            .collect();
+        if !elsewhere.is_empty() {
+            return format!(
+                "Snapshot {} was saved in {}, not here. Run it from there or pass it with --dir.",
+                name,
+                elsewhere.join(", ")
+            );
+        }
+        let available: Vec<&str> = snapshots.keys().map(|k| k.as_str()).collect();
+        format!(
+            "Unknown snapshot: {}\nAvailable: {}",
+            name,
+            if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            }
+        )
+    })
+}
+
+/// Records the stacks running right now under `name`, replacing any
+/// snapshot with the same name.
+pub fn save_snapshot(name: &str, statuses: &[ServiceStatus]) -> Result<Snapshot, String> {
+    validate_name(name)?;
+    let snapshot = Snapshot {
+        created: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        running: statuses
+            .iter()
+            .filter(|s| s.running)
+            .map(|s| s.name.clone())
+            .collect(),
+    };
+
+    update_project(|state| {
+        state.snapshots.insert(name.to_string(), snapshot.clone());
+    })?;
+    Ok(snapshot)
+}
+
+pub fn delete_snapshot(name: &str) -> Result<(), String> {
+    get_snapshot(name)?;
+    update_project(|state| {
+        state.snapshots.remove(name);
+    })
+}
+
+/// Starting what the snapshot had running and stopping whatever else runs
+/// brings the workspace back to exactly the snapshot.
+pub fn restore_plan(
+    snapshot: &Snapshot,
+    services: &[Service],
+    statuses: &[ServiceStatus],
+) -> RestorePlan {
+    let is_running = |service: &Service| {
+        statuses
+            .iter()
+            .any(|st| st.name == service.name && st.running)
+    };
+
+    RestorePlan {
+        to_start: services
+            .iter()
+            .filter(|s| snapshot.running.contains(&s.name) && !is_running(s))
+            .cloned()
+            .collect(),
+        to_stop: services
+            .iter()
+            .filter(|s| !snapshot.running.contains(&s.name) && is_running(s))
+            .cloned()
+            .collect(),
+        missing: snapshot
+            .running
+            .iter()
+            .filter(|name| !services.iter().any(|s| &s.name == *name))
+            .cloned()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::State;
+    use std::path::PathBuf;
+
+    fn service(name: &str) -> Service {
+        Service {
+            name: name.to_string(),
+            path: PathBuf::from("/nonexistent").join(name),
+            compose_file: "docker-compose.yml".to_string(),
+        }
+    }
+
+    fn status(name: &str, running: bool) -> ServiceStatus {
+        ServiceStatus {
+            name: name.to_string(),
+            path: PathBuf::from("/nonexistent").join(name),
+            compose_file: "docker-compose.yml".to_string(),
+            state: if running { State::Up } else { State::Down },
+            running,
+            container_count: 0,
+            containers: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn names(services: &[Service]) -> Vec<&str> {
+        services.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn restores_exactly_the_recorded_set() {
+        let snapshot = Snapshot {
+            created: "2026-01-01 09:00".to_string(),
+            running: vec!["api".to_string(), "db".to_string(), "gone".to_string()],
+        };
+        let services = vec![service("api"), service("db"), service("web")];
+        let statuses = vec![
+            status("api", true),
+            status("db", false),
+            status("web", true),
+        ];
+
+        let plan = restore_plan(&snapshot, &services, &statuses);
+        assert_eq!(names(&plan.to_start), ["db"]);
+        assert_eq!(names(&plan.to_stop), ["web"]);
+        assert_eq!(plan.missing, ["gone"]);
+        assert!(!plan.is_empty());
+    }
+
+    #[test]
+    fn nothing_to_do_when_running_set_matches() {
+        let snapshot = Snapshot {
+            created: "2026-01-01 09:00".to_string(),
+            running: vec!["api".to_string()],
+        };
+        let services = vec![service("api"), service("db")];
+        let statuses = vec![status("api", true), status("db", false)];
+        assert!(restore_plan(&snapshot, &services, &statuses).is_empty());
+    }
+
+    #[test]
+    fn validates_names() {
+        assert!(validate_name("feature-x").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("two words").is_err());
+    }
+}
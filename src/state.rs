@@ -0,0 +1,90 @@
+use crate::config::{get_config_path, toml_error};
+use crate::snapshot::Snapshot;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+/// What dockerstrator remembers between runs that isn't a setting. Lives in
+/// `state.toml` next to `config.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppState {
+    /// Canonical target directory -> what was remembered while working there
+    #[serde(default)]
+    pub projects: BTreeMap<String, ProjectState>,
+}
+
+/// State remembered for one target directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectState {
+    /// Last menu action that picked services
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_action: Option<String>,
+    /// Action -> services picked for it last time
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub selections: BTreeMap<String, Vec<String>>,
+    /// Snapshot name -> running set it recorded
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub snapshots: BTreeMap<String, Snapshot>,
+}
+
+pub fn get_state_path() -> PathBuf {
+    get_config_path().with_file_name("state.toml")
+}
+
+/// The saved state; a missing file is an empty state. A file that doesn't
+/// parse is an error, not a blank slate that would be saved over it.
+pub fn read_state() -> Result<AppState, String> {
+    let state_path = get_state_path();
+    let contents = match fs::read_to_string(&state_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(AppState::default()),
+        Err(e) => return Err(format!("{}: {}", state_path.display(), e)),
+    };
+    toml::from_str(&contents).map_err(|e| toml_error(&state_path, &contents, &e))
+}
+
+/// The saved state for reading only; empty if it can't be read.
+pub fn load_state() -> AppState {
+    read_state().unwrap_or_default()
+}
+
+/// Applies `change` to the saved state and writes it back. Refuses when the
+/// state file doesn't parse, so nothing in it gets lost.
+pub fn update_state<T>(change: impl FnOnce(&mut AppState) -> T) -> Result<T, String> {
+    let mut state = read_state()?;
+    let result = change(&mut state);
+    save_state(&state)?;
+    Ok(result)
+}
+
+/// The directory being worked in, as the key its state is stored under.
+pub fn project_key() -> String {
+    let dir = std::env::current_dir().unwrap_or_default();
+    dir.canonicalize().unwrap_or(dir).display().to_string()
+}
+
+/// The state remembered for the current target directory.
+pub fn load_project() -> ProjectState {
+    load_state()
+        .projects
+        .remove(&project_key())
+        .unwrap_or_default()
+}
+
+/// Like [`update_state`], for the current target directory's state.
+pub fn update_project<T>(change: impl FnOnce(&mut ProjectState) -> T) -> Result<T, String> {
+    update_state(|state| change(state.projects.entry(project_key()).or_default()))
+}
+
+fn save_state(state: &AppState) -> Result<(), String> {
+    let state_path = get_state_path();
+
+    if let Some(parent) = state_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let contents = toml::to_string_pretty(state).map_err(|e| e.to_string())?;
+    fs::write(&state_path, contents).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use crate::config::Config;
+use crate::docker::{output_with_timeout, query_timeout, ContainerStatus, ServiceStatus};
+use crate::events::current_statuses;
+use crate::service::Service;
+use colored::*;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{poll, read, Event, KeyCode, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use serde::Deserialize;
+use std::io::{stdout, Write};
+use std::process::Command;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const LIVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// One line of `docker stats --no-stream --format '{{json .}}'`.
+#[derive(Deserialize)]
+struct StatsEntry {
+    #[serde(rename = "ID", default)]
+    id: String,
+    #[serde(rename = "Name", default)]
+    name: String,
+    #[serde(rename = "CPUPerc", default)]
+    cpu: String,
+    #[serde(rename = "MemUsage", default)]
+    mem_usage: String,
+    #[serde(rename = "NetIO", default)]
+    net_io: String,
+    #[serde(rename = "BlockIO", default)]
+    block_io: String,
+}
+
+impl StatsEntry {
+    /// docker-compose v1 statuses only know full container IDs, of which
+    /// `docker stats` prints the short form.
+    fn is_for(&self, container: &ContainerStatus) -> bool {
+        container.name == self.name || (!self.id.is_empty() && container.name.starts_with(&self.id))
+    }
+}
+
+/// Resource usage of one discovered service, summed across its containers.
+#[derive(Clone, Debug, Default)]
+pub struct ServiceUsage {
+    pub name: String,
+    pub containers: usize,
+    pub cpu_percent: f64,
+    pub memory: f64,
+    pub net_rx: f64,
+    pub net_tx: f64,
+    pub block_read: f64,
+    pub block_write: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortBy {
+    Cpu,
+    Memory,
+}
+
+/// Parses docker's human-readable sizes ("12.5MiB", "1.2kB", "0B") into bytes.
+fn parse_size(value: &str) -> f64 {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.trim().parse().unwrap_or(0.0);
+    let multiplier = match unit.trim() {
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => 1.0,
+    };
+    number * multiplier
+}
+
+/// Splits "used / total" style pairs into their two sizes.
+fn parse_pair(value: &str) -> (f64, f64) {
+    let mut parts = value.split('/');
+    let first = parts.next().map(parse_size).unwrap_or(0.0);
+    let second = parts.next().map(parse_size).unwrap_or(0.0);
+    (first, second)
+}
+
+/// Formats like docker does: binary units for memory, decimal ones for I/O.
+fn format_bytes(bytes: f64, binary: bool) -> String {
+    let (base, units) = if binary {
+        (1024.0, ["B", "KiB", "MiB", "GiB", "TiB"])
+    } else {
+        (1000.0, ["B", "kB", "MB", "GB", "TB"])
+    };
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0}{}", value, units[unit])
+    } else {
+        format!("{:.1}{}", value, units[unit])
+    }
+}
+
+/// Takes one `docker stats` sample and sums it per service.
+pub fn collect_usage(services: &[Service], legacy: bool) -> Vec<ServiceUsage> {
+    let statuses: Vec<ServiceStatus> = current_statuses(services, legacy)
+        .into_iter()
+        .filter(|s| s.running)
+        .collect();
+
+    let output = output_with_timeout(
+        Command::new("docker").args(["stats", "--no-stream", "--format", "{{json .}}"]),
+        query_timeout(),
+    )
+    .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
+    .unwrap_or_default();
+    let entries: Vec<StatsEntry> = output
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    statuses
+        .iter()
+        .map(|status| {
+            let mut usage = ServiceUsage {
+                name: status.name.clone(),
+                ..Default::default()
+            };
+            for entry in entries
+                .iter()
+                .filter(|e| status.containers.iter().any(|c| e.is_for(c)))
+            {
+                let (net_rx, net_tx) = parse_pair(&entry.net_io);
+                let (block_read, block_write) = parse_pair(&entry.block_io);
+                usage.containers += 1;
+                usage.cpu_percent += entry.cpu.trim_end_matches('%').parse().unwrap_or(0.0);
+                usage.memory += parse_pair(&entry.mem_usage).0;
+                usage.net_rx += net_rx;
+                usage.net_tx += net_tx;
+                usage.block_read += block_read;
+                usage.block_write += block_write;
+            }
+            usage
+        })
+        .collect()
+}
+
+fn spawn_sampler(services: Vec<Service>, legacy: bool, tx: Sender<Vec<ServiceUsage>>) {
+    thread::spawn(move || {
+        let _ = tx.send(collect_usage(&services, legacy));
+    });
+}
+
+fn draw(usage: &[ServiceUsage], sort: SortBy, live: bool, sampling: bool) {
+    let mut out = stdout().lock();
+    let _ = execute!(out, MoveTo(0, 0), Clear(ClearType::All));
+
+    let mode = match (live, sampling) {
+        (_, true) => "sampling...".to_string(),
+        (true, false) => format!("live, every {}s", LIVE_INTERVAL.as_secs()),
+        (false, false) => "snapshot".to_string(),
+    };
+    let sort_label = match sort {
+        SortBy::Cpu => "CPU",
+        SortBy::Memory => "memory",
+    };
+    let _ = write!(
+        out,
+        "\r\n{}  {}\r\n\r\n",
+        "Resource Usage".bold().cyan(),
+        format!("({}, sorted by {})", mode, sort_label).bright_black()
+    );
+
+    if usage.is_empty() && !sampling {
+        let _ = write!(
+            out,
+            "  {}\r\n",
+            "No services are currently running.".yellow()
+        );
+    } else {
+        let width = usage
+            .iter()
+            .map(|u| u.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("NAME".len());
+        let _ = write!(
+            out,
+            "  {}\r\n",
+            format!(
+                "{:<width$}  {:>4}  {:>8}  {:>10}  {:>21}  {:>21}",
+                "NAME",
+                "CTRS",
+                "CPU %",
+                "MEMORY",
+                "NET RX / TX",
+                "BLOCK R / W",
+                width = width
+            )
+            .bright_black()
+        );
+        for u in usage {
+            let _ = write!(
+                out,
+                "  {}  {:>4}  {:>8}  {:>10}  {:>21}  {:>21}\r\n",
+                format!("{:<width$}", u.name, width = width).cyan(),
+                u.containers,
+                format!("{:.2}%", u.cpu_percent),
+                format_bytes(u.memory, true),
+                format!(
+                    "{} / {}",
+                    format_bytes(u.net_rx, false),
+                    format_bytes(u.net_tx, false)
+                ),
+                format!(
+                    "{} / {}",
+                    format_bytes(u.block_read, false),
+                    format_bytes(u.block_write, false)
+                ),
+            );
+        }
+    }
+
+    let _ = write!(
+        out,
+        "\r\n{}",
+        "c sort by CPU  m sort by memory  l toggle live  r refresh  q back".bright_black()
+    );
+    let _ = out.flush();
+}
+
+fn sort_usage(usage: &mut [ServiceUsage], sort: SortBy) {
+    match sort {
+        SortBy::Cpu => usage.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent)),
+        SortBy::Memory => usage.sort_by(|a, b| b.memory.total_cmp(&a.memory)),
+    }
+}
+
+pub fn show_usage(services: &[Service], config: &Config) {
+    let legacy = config.legacy_compose;
+    let (tx, rx) = mpsc::channel();
+    let mut usage: Vec<ServiceUsage> = Vec::new();
+    let mut sort = SortBy::Cpu;
+    let mut live = false;
+    let mut sampling = true;
+    let mut last_sample = Instant::now();
+    spawn_sampler(services.to_vec(), legacy, tx.clone());
+
+    let _ = execute!(stdout(), Hide);
+    let _ = enable_raw_mode();
+    draw(&usage, sort, live, sampling);
+
+    loop {
+        let mut dirty = false;
+
+        if let Ok(true) = poll(Duration::from_millis(100)) {
+            if let Ok(Event::Key(key)) = read() {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    _ if ctrl_c => break,
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Char('c') => sort = SortBy::Cpu,
+                    KeyCode::Char('m') => sort = SortBy::Memory,
+                    KeyCode::Char('l') => live = !live,
+                    KeyCode::Char('r') if !sampling => {
+                        sampling = true;
+                        spawn_sampler(services.to_vec(), legacy, tx.clone());
+                    }
+                    _ => {}
+                }
+                sort_usage(&mut usage, sort);
+                dirty = true;
+            }
+        }
+
+        if let Ok(sample) = rx.try_recv() {
+            usage = sample;
+            sort_usage(&mut usage, sort);
+            sampling = false;
+            last_sample = Instant::now();
+            dirty = true;
+        }
+
+        if live && !sampling && last_sample.elapsed() >= LIVE_INTERVAL {
+            sampling = true;
+            spawn_sampler(services.to_vec(), legacy, tx.clone());
+            dirty = true;
+        }
+
+        if dirty {
+            draw(&usage, sort, live, sampling);
+        }
+    }
+
+    let _ = disable_raw_mode();
+    let _ = execute!(stdout(), Show);
+}
@@ -1,9 +1,17 @@
+use crate::docker::{Progress, State};
+use crate::service::Service;
 use colored::*;
-use crossterm::cursor::MoveTo;
+use crossterm::cursor::{MoveTo, MoveUp};
 use crossterm::event::{read, Event, KeyCode};
 use crossterm::execute;
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
-use std::io::{stdout, Write};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
+    LeaveAlternateScreen,
+};
+use std::io::{stdout, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 pub fn clear_screen() {
     let _ = execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0));
@@ -51,7 +59,7 @@ pub fn interactive_menu(title: &str, items: &[(&str, &str)]) -> Option<char> {
                 }
                 KeyCode::Char(c) => {
                     let c = c.to_ascii_lowercase();
-                    if items.iter().any(|(k, _)| k.chars().next() == Some(c)) {
+                    if items.iter().any(|(k, _)| k.starts_with(c)) {
                         let _ = disable_raw_mode();
                         break Some(c);
                     }
@@ -67,7 +75,159 @@ pub fn interactive_menu(title: &str, items: &[(&str, &str)]) -> Option<char> {
     result
 }
 
+/// Hands the real terminal to a child process (e.g. an interactive shell) and
+/// restores the alternate screen afterwards.
+pub fn with_terminal_released<T>(f: impl FnOnce() -> T) -> T {
+    let _ = disable_raw_mode();
+    let _ = execute!(stdout(), LeaveAlternateScreen);
+    let result = f();
+    let _ = execute!(stdout(), EnterAlternateScreen);
+    clear_screen();
+    result
+}
+
 pub fn pause() {
     println!("{}", "Press ENTER to continue...".bright_black());
     let _ = std::io::stdin().read_line(&mut String::new());
 }
+
+pub fn state_color(state: State) -> Color {
+    match state {
+        State::Up => Color::Green,
+        State::Partial | State::Unhealthy => Color::Yellow,
+        State::Exited | State::Down => Color::Red,
+        State::Unknown => Color::Magenta,
+    }
+}
+
+pub fn state_label(state: State) -> ColoredString {
+    state.label().color(state_color(state))
+}
+
+struct Finished {
+    label: &'static str,
+    color: Color,
+    elapsed: Duration,
+}
+
+/// Live table of the services in a parallel operation: a spinner and elapsed
+/// time per row, switching to a result label (OK/ERROR by default) as each one
+/// finishes. When stdout isn't a terminal, or the table wouldn't fit on it, it
+/// only prints each row once, as it finishes.
+pub struct ProgressTable {
+    /// Service name and, once it's done, how it ended
+    rows: Vec<(String, Option<Finished>)>,
+    width: usize,
+    /// What unfinished rows say next to the spinner, e.g. "running"
+    activity: &'static str,
+    started: Instant,
+    frame: usize,
+    live: bool,
+    drawn: bool,
+}
+
+impl ProgressTable {
+    pub fn new(services: &[Service], activity: &'static str) -> ProgressTable {
+        let mut table = ProgressTable {
+            rows: services.iter().map(|s| (s.name.clone(), None)).collect(),
+            width: services.iter().map(|s| s.name.len()).max().unwrap_or(0),
+            activity,
+            started: Instant::now(),
+            frame: 0,
+            live: false,
+            drawn: false,
+        };
+        // Redrawing moves the cursor up over every row, which can't go past
+        // the top of the screen
+        let height = size().map(|(_, rows)| rows as usize).unwrap_or(0);
+        table.live = stdout().is_terminal() && table.rows.len() < height;
+        if table.live {
+            table.draw();
+        }
+        table
+    }
+
+    pub fn update(&mut self, progress: Progress) {
+        match progress {
+            Progress::Tick => self.tick(),
+            Progress::Done(result) if result.ok => self.finish(&result.name, "OK", Color::Green),
+            Progress::Done(result) => self.finish(&result.name, "ERROR", Color::Red),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.frame += 1;
+        if self.live {
+            self.draw();
+        }
+    }
+
+    pub fn finish(&mut self, name: &str, label: &'static str, color: Color) {
+        let elapsed = self.started.elapsed();
+        let row = self
+            .rows
+            .iter()
+            .position(|(row, done)| row == name && done.is_none());
+        if let Some(i) = row {
+            self.rows[i].1 = Some(Finished {
+                label,
+                color,
+                elapsed,
+            });
+            if self.live {
+                self.draw();
+            } else {
+                println!("{}", self.row_text(i));
+            }
+        }
+    }
+
+    /// Erases the table, for callers that print their own per-service results.
+    pub fn clear(&mut self) {
+        if self.drawn && !self.rows.is_empty() {
+            let _ = execute!(
+                stdout(),
+                MoveUp(self.rows.len() as u16),
+                Clear(ClearType::FromCursorDown)
+            );
+            self.drawn = false;
+        }
+    }
+
+    fn row_text(&self, i: usize) -> String {
+        let (name, done) = &self.rows[i];
+        let name = format!("{:<width$}", name, width = self.width).cyan();
+        let (state, elapsed) = match done {
+            Some(finished) => (
+                format!("{:<9}", finished.label).color(finished.color),
+                finished.elapsed,
+            ),
+            None => (
+                format!("{} {}", SPINNER[self.frame % SPINNER.len()], self.activity).yellow(),
+                self.started.elapsed(),
+            ),
+        };
+        format!(
+            "  {}  {}  {}",
+            name,
+            state,
+            format!("{:.1}s", elapsed.as_secs_f64()).bright_black()
+        )
+    }
+
+    fn draw(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let mut out = stdout().lock();
+        if self.drawn {
+            let _ = execute!(out, MoveUp(self.rows.len() as u16));
+        }
+        for i in 0..self.rows.len() {
+            let _ = execute!(out, Clear(ClearType::CurrentLine));
+            let _ = writeln!(out, "{}", self.row_text(i));
+        }
+        let _ = out.flush();
+        self.drawn = true;
+    }
+}